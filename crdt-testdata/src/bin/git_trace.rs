@@ -0,0 +1,17 @@
+use std::path::Path;
+use crdt_testdata::{load_git_history, save_testing_data};
+
+/// Convert the git history of one file into a trace.
+///
+/// Usage: git_trace <repo> <file> <out.json.gz>
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("Usage: {} <repo> <file> <out.json.gz>", args[0]);
+        std::process::exit(1);
+    }
+
+    let data = load_git_history(Path::new(&args[1]), &args[2]).unwrap();
+    println!("{} commits, {} patches", data.txns.len(), data.len());
+    save_testing_data(&args[3], &data);
+}
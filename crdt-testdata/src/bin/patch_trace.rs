@@ -0,0 +1,25 @@
+use std::fs::read_to_string;
+use crdt_testdata::{load_patch_series, save_testing_data};
+
+/// Convert a series of unified diffs into a trace.
+///
+/// Usage: patch_trace <start file> <end file> <out.json.gz> <patch>...
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 5 {
+        eprintln!("Usage: {} <start file> <end file> <out.json.gz> <patch>...", args[0]);
+        std::process::exit(1);
+    }
+
+    let start = read_to_string(&args[1]).unwrap();
+    let end = read_to_string(&args[2]).unwrap();
+    let diffs: Vec<String> = args[4..].iter().map(|f| read_to_string(f).unwrap()).collect();
+    let diffs: Vec<&str> = diffs.iter().map(String::as_str).collect();
+
+    let data = load_patch_series(&start, &diffs, &end).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    println!("{} diffs, {} patches", data.txns.len(), data.len());
+    save_testing_data(&args[3], &data);
+}
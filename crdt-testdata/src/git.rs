@@ -0,0 +1,128 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use similar::{DiffTag, TextDiff};
+use crate::{TestData, TestPatch, TestTxn};
+
+/// Build a trace from the history of a single file in a local git repository. Each commit (along
+/// the first-parent chain) which touches the file becomes one `TestTxn`, holding the line-level
+/// changes between the file's contents before and after that commit.
+///
+/// `file` is relative to `repo`. The trace starts from an empty document, so the first commit
+/// inserts the whole file. Positions are unicode codepoints, like the other datasets. Renames
+/// aren't followed, and commits where the file isn't valid UTF-8 are skipped.
+pub fn load_git_history(repo: &Path, file: &str) -> io::Result<TestData> {
+    let log = git(repo, &["log", "--reverse", "--first-parent", "--format=%H", "--", file])?;
+    let log = String::from_utf8_lossy(&log);
+
+    let mut txns = vec![];
+    let mut prev = String::new();
+    for commit in log.lines() {
+        // The file is missing from a commit which deleted it, which deletes all its text. Any
+        // other failure is an error.
+        let listed = git(repo, &["ls-tree", "--name-only", commit, "--", file])?;
+        let content = if listed.is_empty() {
+            String::new()
+        } else {
+            match String::from_utf8(git(repo, &["show", &format!("{commit}:./{file}")])?) {
+                Ok(content) => content,
+                Err(_) => continue,
+            }
+        };
+
+        let patches = line_patches(&prev, &content);
+        if !patches.is_empty() {
+            txns.push(TestTxn { patches });
+        }
+        prev = content;
+    }
+
+    Ok(TestData {
+        using_byte_positions: false,
+        start_content: String::new(),
+        end_content: prev,
+        txns,
+    })
+}
+
+fn git(repo: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output()?;
+    if !output.status.success() {
+        let msg = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(msg));
+    }
+    Ok(output.stdout)
+}
+
+/// Diff two versions of a document by line, returning the patches (in codepoints) which turn
+/// `old` into `new` when applied in order.
+pub(crate) fn line_patches(old: &str, new: &str) -> Vec<TestPatch> {
+    let diff = TextDiff::from_lines(old, new);
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let chars = |lines: &[&str]| lines.iter().map(|l| l.chars().count()).sum::<usize>();
+
+    // Patches are applied front to back, so everything before the current op already matches
+    // the new document.
+    let mut pos = 0;
+    let mut patches = vec![];
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let ins_len = chars(&new_lines[new_range.clone()]);
+        if tag != DiffTag::Equal {
+            let del = chars(&old_lines[old_range]);
+            let ins: String = new_lines[new_range].concat();
+            patches.push(TestPatch(pos, del, ins));
+        }
+        pos += ins_len;
+    }
+    patches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use crate::TestPatch;
+    use super::{git, line_patches, load_git_history};
+
+    #[test]
+    fn line_patches_replay() {
+        let old = "fn main() {\n    println!(\"hi\");\n}\n";
+        let new = "// ツ\nfn main() {\n    println!(\"héllo\");\n    return;\n}\n";
+        let patches = line_patches(old, new);
+
+        let mut r = ropey::Rope::from_str(old);
+        for TestPatch(pos, del, ins) in &patches {
+            r.remove(*pos..*pos + *del);
+            r.insert(*pos, ins);
+        }
+        assert_eq!(r.to_string(), new);
+    }
+
+    #[test]
+    fn deleted_file() {
+        let repo = std::env::temp_dir().join(format!("crdt-testdata-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(repo.join("src")).unwrap();
+        let commit = |repo: &Path| {
+            git(repo, &["add", "-A"]).unwrap();
+            git(repo, &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", "edit"]).unwrap();
+        };
+        git(&repo, &["init", "-q"]).unwrap();
+        fs::write(repo.join("src/a.txt"), "one\ntwo\n").unwrap();
+        commit(&repo);
+        fs::remove_file(repo.join("src/a.txt")).unwrap();
+        fs::write(repo.join("b.txt"), "keep the commit non-empty\n").unwrap();
+        commit(&repo);
+
+        let data = load_git_history(&repo, "src/a.txt").unwrap();
+        assert_eq!(data.txns.len(), 2);
+        assert_eq!(data.end_content, "");
+        assert_eq!(data.validate(), Ok(()));
+
+        // A repo git can't read is an error, not a deleted file.
+        assert!(load_git_history(&repo.join("missing"), "src/a.txt").is_err());
+        fs::remove_dir_all(&repo).unwrap();
+    }
+}
@@ -1,23 +1,36 @@
 // use std::time::SystemTime;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, BufWriter, Read, Write};
 use flate2::bufread::GzDecoder;
-use serde::Deserialize;
-
-/// This file contains some simple helpers for loading test data. Its used by benchmarking and
-/// testing code.
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+mod git;
+mod line_endings;
+mod minimize;
+mod patch;
+mod validate;
+pub use git::load_git_history;
+pub use line_endings::{convert_line_endings, LineEnding};
+pub use minimize::minimize;
+pub use patch::{load_patch_series, PatchError};
+pub use validate::ValidationError;
+
+// This file contains some simple helpers for loading test data. Its used by benchmarking and
+// testing code.
 
 /// (position, delete length, insert content).
-#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 pub struct TestPatch(pub usize, pub usize, pub String);
 
-#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 pub struct TestTxn {
     // time: String, // ISO String. Unused.
     pub patches: Vec<TestPatch>
 }
 
-#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 pub struct TestData {
     #[serde(default)]
     pub using_byte_positions: bool,
@@ -46,7 +59,7 @@ impl TestData {
     pub fn chars_to_bytes(&self) -> Self {
         assert!(!self.using_byte_positions);
 
-        let mut r = ropey::Rope::new();
+        let mut r = ropey::Rope::from_str(&self.start_content);
 
         Self {
             using_byte_positions: true,
@@ -100,16 +113,51 @@ pub fn load_testing_data(filename: &str) -> TestData {
     data
 }
 
+/// Write test data out in the same gzipped JSON format read by `load_testing_data`.
+pub fn save_testing_data(filename: &str, data: &TestData) {
+    let file = File::create(filename).unwrap();
+    let mut writer = GzEncoder::new(BufWriter::new(file), Compression::default());
+    serde_json::to_writer(&mut writer, data).unwrap();
+    writer.finish().unwrap().flush().unwrap();
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{load_testing_data, TestData, TestPatch, TestTxn};
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn it_works() {
         let data = load_testing_data("../benchmark_data/sveltecomponent.json.gz");
         assert!(data.txns.len() > 0);
     }
 
+    #[test]
+    fn convert_chars_to_bytes_with_start() {
+        let data = TestData {
+            using_byte_positions: false,
+            start_content: "héllo ツ".to_string(),
+            end_content: "héllo ツx".to_string(),
+            txns: vec![
+                TestTxn {
+                    patches: vec![
+                        TestPatch(7, 0, "x".into()),
+                        TestPatch(1, 1, "e".into()),
+                        TestPatch(1, 1, "é".into()),
+                    ],
+                }
+            ],
+        };
+
+        let data2 = data.chars_to_bytes();
+        let patches: Vec<_> = data2.patches().cloned().collect();
+        assert_eq!(patches, vec![
+            TestPatch(10, 0, "x".into()),
+            TestPatch(1, 2, "e".into()),
+            TestPatch(1, 1, "é".into()),
+        ]);
+    }
+
     #[test]
     fn convert_chars_to_bytes() {
         let data = TestData {
@@ -0,0 +1,124 @@
+use crate::{TestData, TestPatch, TestTxn};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Alternating `\n` and `\r\n`, starting with `\n`.
+    Mixed,
+}
+
+impl LineEnding {
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+            LineEnding::Mixed => "mixed",
+        }
+    }
+}
+
+/// Rewrites `\n`s, keeping track of where it is in the `Mixed` alternation.
+struct Converter {
+    ending: LineEnding,
+    newlines: usize,
+}
+
+impl Converter {
+    fn convert(&mut self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        for c in text.chars() {
+            if c == '\n' {
+                let crlf = match self.ending {
+                    LineEnding::Lf => false,
+                    LineEnding::Crlf => true,
+                    LineEnding::Mixed => self.newlines % 2 == 1,
+                };
+                if crlf {
+                    result.push('\r');
+                }
+                self.newlines += 1;
+            }
+            result.push(c);
+        }
+        result
+    }
+}
+
+/// Convert LF-only text to use different line endings.
+pub fn convert_line_endings(text: &str, ending: LineEnding) -> String {
+    Converter { ending, newlines: 0 }.convert(text)
+}
+
+/// Line breaks which ropey may count as well as `\n`. Traces containing them can't be converted,
+/// because the converted positions are found by line and column.
+const OTHER_BREAKS: &[char] = &['\r', '\u{0B}', '\u{0C}', '\u{85}', '\u{2028}', '\u{2029}'];
+
+impl TestData {
+    /// Convert an LF-only trace to use different line endings. Every `\n` in the start content
+    /// and inserted text is rewritten, and positions are moved to match, so each patch edits the
+    /// same line and column as before.
+    ///
+    /// Panics if the trace uses byte positions or contains any other kind of line break.
+    pub fn with_line_endings(&self, ending: LineEnding) -> Self {
+        assert!(!self.using_byte_positions);
+        let has_breaks = |s: &str| s.contains(OTHER_BREAKS);
+        assert!(!has_breaks(&self.start_content) && !self.patches().any(|p| has_breaks(&p.2)),
+            "Trace already contains other line breaks");
+
+        let mut converter = Converter { ending, newlines: 0 };
+        let start_content = converter.convert(&self.start_content);
+        let mut lf = ropey::Rope::from_str(&self.start_content);
+        let mut converted = ropey::Rope::from_str(&start_content);
+        // Lines line up between the two documents, and only differ in how they end.
+        let map = |lf: &ropey::Rope, converted: &ropey::Rope, pos: usize| {
+            let line = lf.char_to_line(pos);
+            converted.line_to_char(line) + pos - lf.line_to_char(line)
+        };
+
+        let txns = self.txns.iter().map(|txn| {
+            TestTxn {
+                patches: txn.patches.iter().map(|TestPatch(pos, del, ins)| {
+                    let start = map(&lf, &converted, *pos);
+                    let end = map(&lf, &converted, pos + del);
+                    let ins_converted = converter.convert(ins);
+
+                    lf.remove(*pos..pos + del);
+                    lf.insert(*pos, ins);
+                    converted.remove(start..end);
+                    converted.insert(start, &ins_converted);
+                    TestPatch(start, end - start, ins_converted)
+                }).collect(),
+            }
+        }).collect();
+
+        Self {
+            using_byte_positions: false,
+            start_content,
+            end_content: converted.to_string(),
+            txns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load_testing_data;
+    use super::{convert_line_endings, LineEnding};
+
+    #[test]
+    fn convert_text() {
+        assert_eq!(convert_line_endings("a\nb\nc\n", LineEnding::Crlf), "a\r\nb\r\nc\r\n");
+        assert_eq!(convert_line_endings("a\nb\nc\n", LineEnding::Mixed), "a\nb\r\nc\n");
+    }
+
+    #[test]
+    fn convert_trace() {
+        let data = load_testing_data("../benchmark_data/sveltecomponent.json.gz");
+        for ending in [LineEnding::Crlf, LineEnding::Mixed] {
+            let converted = data.with_line_endings(ending);
+            assert_eq!(converted.validate(), Ok(()));
+            assert_eq!(converted.end_content.replace('\r', ""), data.end_content);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{TestData, TestPatch, TestTxn};
+use crate::validate::replay;
+
+/// Build a candidate trace, or None if the patches don't replay cleanly on `start`.
+fn candidate(data: &TestData, start: &str, patches: &[TestPatch]) -> Option<TestData> {
+    let end = replay(start, patches, data.using_byte_positions).ok()?;
+    Some(TestData {
+        using_byte_positions: data.using_byte_positions,
+        start_content: start.to_string(),
+        end_content: end,
+        txns: vec![TestTxn { patches: patches.to_vec() }],
+    })
+}
+
+/// Shrink a trace which triggers a bug down to a small reproduction, using delta debugging.
+///
+/// `fails` is called on candidate traces and should return true if the bug still reproduces
+/// (it will typically replay the trace on a rope and compare against `end_content`). Candidates
+/// are always internally consistent: `end_content` is recomputed for each one, and patch lists
+/// which would go out of bounds are never tried. The result is a single transaction.
+///
+/// Panics if `data` doesn't fail to begin with.
+pub fn minimize(data: &TestData, mut fails: impl FnMut(&TestData) -> bool) -> TestData {
+    assert!(fails(data), "The trace must fail before it can be minimised");
+    let mut test = |start: &str, patches: &[TestPatch]| {
+        candidate(data, start, patches).filter(|c| fails(c))
+    };
+
+    let mut start = data.start_content.clone();
+    let mut patches: Vec<TestPatch> = data.patches().cloned().collect();
+    let mut best = test(&start, &patches).expect("Trace doesn't replay cleanly");
+
+    // Cut the trace off after the first failing patch. Failure usually persists once it shows up,
+    // so this is a binary search for the shortest failing prefix.
+    let (mut lo, mut hi) = (0, patches.len());
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        match test(&start, &patches[..mid]) {
+            Some(c) => { best = c; hi = mid; }
+            None => lo = mid + 1,
+        }
+    }
+    patches.truncate(hi);
+
+    // Fold as many leading patches as possible into the start content.
+    let (mut lo, mut hi) = (0, patches.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        let folded = replay(&start, &patches[..mid], data.using_byte_positions).unwrap();
+        match test(&folded, &patches[mid..]) {
+            Some(c) => { best = c; lo = mid; }
+            None => hi = mid - 1,
+        }
+    }
+    if lo > 0 {
+        start = replay(&start, &patches[..lo], data.using_byte_positions).unwrap();
+        patches.drain(..lo);
+    }
+
+    // Classic ddmin over the remaining patches, removing ever smaller chunks.
+    let mut n = 2;
+    while patches.len() >= 2 {
+        let chunk = patches.len().div_ceil(n);
+        let mut reduced = false;
+        for i in (0..patches.len()).step_by(chunk) {
+            let mut rest = patches[..i].to_vec();
+            rest.extend_from_slice(&patches[(i + chunk).min(patches.len())..]);
+            if let Some(c) = test(&start, &rest) {
+                best = c;
+                patches = rest;
+                n = (n - 1).max(2);
+                reduced = true;
+                break;
+            }
+        }
+        if !reduced {
+            if n >= patches.len() { break; }
+            n = (n * 2).min(patches.len());
+        }
+    }
+
+    // Make each remaining patch as small as possible.
+    let mut i = 0;
+    while i < patches.len() {
+        let simpler = simplify(&patches[i]).into_iter().find_map(|p| {
+            let mut rest = patches.clone();
+            rest[i] = p;
+            Some((test(&start, &rest)?, rest))
+        });
+        match simpler {
+            Some((c, rest)) => { best = c; patches = rest; }
+            None => i += 1,
+        }
+    }
+
+    // Finally trim the start content, first from the end and then from the front.
+    let mut chunk = start.len() / 2;
+    while chunk > 0 && !start.is_empty() {
+        let mut cut = start.len().saturating_sub(chunk);
+        while !start.is_char_boundary(cut) { cut -= 1; }
+        if cut == start.len() { break; }
+        if let Some(c) = test(&start[..cut], &patches) {
+            best = c;
+            start.truncate(cut);
+        } else {
+            chunk /= 2;
+        }
+    }
+    let mut chunk = start.len() / 2;
+    while chunk > 0 && !data.using_byte_positions {
+        // Dropping leading chars shifts every position back, so this only works when all the
+        // patches can be moved back by the same amount.
+        let mut cut = chunk.min(start.len());
+        while !start.is_char_boundary(cut) { cut -= 1; }
+        let shift = start[..cut].chars().count();
+        let shifted: Option<Vec<TestPatch>> = patches.iter()
+            .map(|TestPatch(pos, del, ins)| Some(TestPatch(pos.checked_sub(shift)?, *del, ins.clone())))
+            .collect();
+        match shifted.and_then(|shifted| Some((test(&start[cut..], &shifted)?, shifted))) {
+            Some((c, shifted)) if cut > 0 => {
+                best = c;
+                start.drain(..cut);
+                patches = shifted;
+            }
+            _ => chunk /= 2,
+        }
+    }
+
+    best
+}
+
+/// Smaller variants of a patch: half the insert, or either (overlapping) half of the deleted
+/// range.
+fn simplify(TestPatch(pos, del, ins): &TestPatch) -> Vec<TestPatch> {
+    let mut result = vec![];
+    let ins_chars = ins.chars().count();
+    if ins_chars > 0 {
+        result.push(TestPatch(*pos, *del, ins.chars().take(ins_chars / 2).collect()));
+    }
+    if *del == 1 {
+        result.push(TestPatch(*pos, 0, ins.clone()));
+    } else if *del > 1 {
+        let half = del - del / 2;
+        result.push(TestPatch(*pos, half, ins.clone()));
+        result.push(TestPatch(pos + del / 2, half, ins.clone()));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TestData, TestPatch, TestTxn};
+    use super::minimize;
+
+    #[test]
+    fn minimize_trace() {
+        // A fake bug: deleting anything which contains an 'x'.
+        let mut patches = vec![];
+        for i in 0..200 {
+            patches.push(TestPatch(i, 0, "ab".into()));
+        }
+        patches.push(TestPatch(100, 0, "x".into()));
+        for i in 0..100 {
+            patches.push(TestPatch(300 - i, 0, "c".into()));
+        }
+        patches.push(TestPatch(99, 3, "".into()));
+        patches.push(TestPatch(0, 5, "".into()));
+
+        let data = TestData {
+            using_byte_positions: false,
+            start_content: "".into(),
+            end_content: "".into(),
+            txns: vec![TestTxn { patches }],
+        };
+        let fails = |d: &TestData| {
+            let mut s: Vec<char> = d.start_content.chars().collect();
+            d.patches().any(|TestPatch(pos, del, ins)| {
+                let removed: Vec<char> = s.splice(*pos..*pos + *del, ins.chars()).collect();
+                removed.contains(&'x')
+            })
+        };
+
+        let min = minimize(&data, fails);
+        assert!(fails(&min));
+        assert_eq!(min.len(), 1);
+        assert_eq!(min.start_content, "x");
+    }
+
+    #[test]
+    fn minimize_ignoring_start() {
+        // A fake bug which doesn't depend on the start content at all: inserting a 'y'.
+        let patches = (0..50)
+            .map(|i| TestPatch(i, 0, if i == 30 { "y" } else { "é" }.into()))
+            .collect();
+        let data = TestData {
+            using_byte_positions: false,
+            start_content: "some starting text ✓".into(),
+            end_content: "".into(),
+            txns: vec![TestTxn { patches }],
+        };
+        let fails = |d: &TestData| d.patches().any(|TestPatch(_, _, ins)| ins.contains('y'));
+
+        let min = minimize(&data, fails);
+        assert!(fails(&min));
+        assert_eq!(min.len(), 1);
+        assert_eq!(min.start_content, "");
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+use crate::{TestData, TestPatch, TestTxn};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PatchError {
+    /// A line of diff `diff` couldn't be parsed.
+    Parse { diff: usize, line: usize, msg: String },
+    /// A context or removed line in diff `diff` doesn't match the document.
+    Mismatch { diff: usize, line: usize },
+    /// All the diffs applied cleanly, but the result isn't the expected final file.
+    EndContent,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Parse { diff, line, msg } => write!(f, "diff {diff}, line {line}: {msg}"),
+            PatchError::Mismatch { diff, line } => {
+                write!(f, "diff {diff}, line {line}: hunk doesn't match the document")
+            }
+            PatchError::EndContent => write!(f, "patched document doesn't match the final file"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Build a trace by applying a series of unified diffs (as produced by `diff -u`, `git diff` or
+/// `git format-patch`) to `start_content`. Each diff becomes one `TestTxn`, with a patch for every
+/// run of changed lines. Positions are unicode codepoints, like the other datasets.
+///
+/// Each diff must touch only one file, and hunks must apply exactly - no fuzz or offsets. The
+/// result is checked against `end_content`.
+pub fn load_patch_series(start_content: &str, diffs: &[&str], end_content: &str) -> Result<TestData, PatchError> {
+    let mut doc = start_content.to_string();
+    let mut txns = vec![];
+    for (i, diff) in diffs.iter().enumerate() {
+        let (patches, new_doc) = apply_diff(&doc, diff, i)?;
+        if !patches.is_empty() {
+            txns.push(TestTxn { patches });
+        }
+        doc = new_doc;
+    }
+
+    if doc != end_content {
+        return Err(PatchError::EndContent);
+    }
+
+    Ok(TestData {
+        using_byte_positions: false,
+        start_content: start_content.to_string(),
+        end_content: doc,
+        txns,
+    })
+}
+
+/// Parse a hunk header like `@@ -12,7 +12,8 @@ fn foo() {` into (old start, old len, new len).
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (old, rest) = rest.split_once(" +")?;
+    let (new, _) = rest.split_once(" @@")?;
+    let range = |s: &str| -> Option<(usize, usize)> {
+        match s.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((s.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = range(old)?;
+    let (_, new_len) = range(new)?;
+    Some((old_start, old_len, new_len))
+}
+
+fn apply_diff(doc: &str, diff: &str, diff_idx: usize) -> Result<(Vec<TestPatch>, String), PatchError> {
+    let old_lines: Vec<&str> = doc.split_inclusive('\n').collect();
+    let lines: Vec<&str> = diff.split_inclusive('\n').collect();
+    let parse_err = |line: usize, msg: &str| PatchError::Parse { diff: diff_idx, line: line + 1, msg: msg.to_string() };
+
+    let mut out = String::new();
+    // Position in the new document, in chars.
+    let mut pos = 0;
+    // Next line of the old document which hasn't been copied to `out`.
+    let mut old_idx = 0;
+    let mut patches = vec![];
+    let mut seen_file = false;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("+++ ") {
+            if seen_file {
+                return Err(parse_err(i, "diff touches more than one file"));
+            }
+            seen_file = true;
+            i += 1;
+            continue;
+        }
+        let Some((old_start, mut old_left, mut new_left)) = parse_hunk_header(line) else {
+            // Commit messages, `diff --git` / `index` / `---` headers and the like.
+            i += 1;
+            continue;
+        };
+
+        // When a hunk only inserts, its start is the line *before* the insertion.
+        let hunk_start = if old_left == 0 { old_start } else { old_start.saturating_sub(1) };
+        if hunk_start < old_idx || hunk_start > old_lines.len() {
+            return Err(parse_err(i, "hunk out of order or out of range"));
+        }
+        for l in &old_lines[old_idx..hunk_start] {
+            out.push_str(l);
+            pos += l.chars().count();
+        }
+        old_idx = hunk_start;
+        i += 1;
+
+        // The run of changed lines currently being collected, as (position, del, ins).
+        let mut run: Option<(usize, usize, String)> = None;
+        while old_left > 0 || new_left > 0 {
+            let Some(&line) = lines.get(i) else {
+                return Err(parse_err(i, "unexpected end of hunk"));
+            };
+            // Some tools strip the trailing space off empty context lines.
+            let (tag, mut content) = match line.chars().next() {
+                Some('\n') => (' ', line),
+                Some(c @ (' ' | '-' | '+')) => (c, &line[1..]),
+                _ => return Err(parse_err(i, "invalid line in hunk")),
+            };
+            if lines.get(i + 1).is_some_and(|l| l.starts_with('\\')) {
+                // "\ No newline at end of file"
+                content = content.strip_suffix('\n').unwrap_or(content);
+                i += 1;
+            }
+
+            if tag == '+' {
+                if new_left == 0 {
+                    return Err(parse_err(i, "too many lines in hunk"));
+                }
+                new_left -= 1;
+                run.get_or_insert((pos, 0, String::new())).2.push_str(content);
+            } else {
+                if old_left == 0 || (tag == ' ' && new_left == 0) {
+                    return Err(parse_err(i, "too many lines in hunk"));
+                }
+                if old_lines.get(old_idx) != Some(&content) {
+                    return Err(PatchError::Mismatch { diff: diff_idx, line: i + 1 });
+                }
+                old_idx += 1;
+                old_left -= 1;
+                if tag == '-' {
+                    run.get_or_insert((pos, 0, String::new())).1 += content.chars().count();
+                } else {
+                    new_left -= 1;
+                    if let Some(patch) = run.take() {
+                        pos = finish_run(&mut patches, &mut out, patch);
+                    }
+                    out.push_str(content);
+                    pos += content.chars().count();
+                }
+            }
+            i += 1;
+        }
+        if let Some(patch) = run.take() {
+            pos = finish_run(&mut patches, &mut out, patch);
+        }
+    }
+
+    for l in &old_lines[old_idx..] {
+        out.push_str(l);
+    }
+    Ok((patches, out))
+}
+
+/// Record a run of changed lines, returning the new document position after it.
+fn finish_run(patches: &mut Vec<TestPatch>, out: &mut String, (pos, del, ins): (usize, usize, String)) -> usize {
+    out.push_str(&ins);
+    let end = pos + ins.chars().count();
+    patches.push(TestPatch(pos, del, ins));
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TestPatch;
+    use super::{load_patch_series, PatchError};
+
+    const START: &str = "a\nb\nc\nd\ne\nf\n";
+    const DIFF1: &str = "\
+From 1234 Mon Sep 17 00:00:00 2001
+Subject: [PATCH] Change some lines
+
+---
+diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,4 @@
+ a
+-b
++bé
+ c
+ d
+@@ -6 +6,2 @@
+ f
++g
+-- 
+2.40.0
+";
+    const DIFF2: &str = "\
+--- a/f.txt
++++ b/f.txt
+@@ -5,3 +5,2 @@
+ e
+-f
+-g
++ツ
+\\ No newline at end of file
+";
+
+    #[test]
+    fn patch_series() {
+        let end = "a\nbé\nc\nd\ne\nツ";
+        let data = load_patch_series(START, &[DIFF1, DIFF2], end).unwrap();
+        assert_eq!(data.txns.len(), 2);
+
+        let mut r = ropey::Rope::from_str(START);
+        for TestPatch(pos, del, ins) in data.patches() {
+            r.remove(*pos..*pos + *del);
+            r.insert(*pos, ins);
+        }
+        assert_eq!(r.to_string(), end);
+    }
+
+    #[test]
+    fn patch_mismatch() {
+        let err = load_patch_series("a\nx\nc\nd\ne\nf\n", &[DIFF1], "").unwrap_err();
+        assert_eq!(err, PatchError::Mismatch { diff: 0, line: 10 });
+    }
+}
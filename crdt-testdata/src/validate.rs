@@ -0,0 +1,176 @@
+use std::fmt;
+use crate::{TestData, TestPatch, TestTxn};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationError {
+    /// The patch (counted across all transactions) reaches past the end of the document. `len` is
+    /// the document length at that point, in the trace's units.
+    OutOfBounds { patch: usize, pos: usize, del: usize, len: usize },
+    /// The patch uses byte positions, but starts or ends inside a multi-byte character. Inserting
+    /// there would produce invalid UTF-8.
+    NotCharBoundary { patch: usize, pos: usize },
+    /// Every patch applied cleanly, but the result isn't `end_content`.
+    EndContent,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::OutOfBounds { patch, pos, del, len } => {
+                write!(f, "patch {patch} ({pos}, {del}) is out of bounds for a document of length {len}")
+            }
+            ValidationError::NotCharBoundary { patch, pos } => {
+                write!(f, "patch {patch}: byte position {pos} isn't on a char boundary")
+            }
+            ValidationError::EndContent => write!(f, "replaying the patches doesn't produce endContent"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Replay `patches` on top of `start` using ropey as a reference, returning the resulting document.
+pub(crate) fn replay<'a>(start: &str, patches: impl IntoIterator<Item=&'a TestPatch>, using_byte_positions: bool) -> Result<String, ValidationError> {
+    let mut r = ropey::Rope::from_str(start);
+    for (i, TestPatch(pos, del, ins)) in patches.into_iter().enumerate() {
+        let len = if using_byte_positions { r.len_bytes() } else { r.len_chars() };
+        let end = match pos.checked_add(*del) {
+            Some(end) if end <= len => end,
+            _ => return Err(ValidationError::OutOfBounds { patch: i, pos: *pos, del: *del, len }),
+        };
+
+        let (pos, end) = if using_byte_positions {
+            let to_char = |byte: usize| {
+                let c = r.byte_to_char(byte);
+                if r.char_to_byte(c) == byte { Ok(c) } else {
+                    Err(ValidationError::NotCharBoundary { patch: i, pos: byte })
+                }
+            };
+            (to_char(*pos)?, to_char(end)?)
+        } else {
+            (*pos, end)
+        };
+        r.remove(pos..end);
+        r.insert(pos, ins);
+    }
+    Ok(r.to_string())
+}
+
+impl TestData {
+    /// Check the trace is internally consistent before benchmarking it: every patch must be in
+    /// bounds (and on char boundaries, for byte positions) when replayed in order from
+    /// `start_content`, and the result must match `end_content`.
+    ///
+    /// Insert strings can't be invalid for either unit on their own. They're `String`s, so they
+    /// hold whole chars, each with a length in chars and in bytes, and loading a trace fails if
+    /// its JSON has a lone UTF-16 surrogate. An insert can only split a char by landing inside
+    /// one, which is reported as `NotCharBoundary`.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let end = replay(&self.start_content, self.patches(), self.using_byte_positions)?;
+        if end != self.end_content {
+            return Err(ValidationError::EndContent);
+        }
+        Ok(())
+    }
+
+    /// The trace cut down to its first `patches` patches, with `end_content` recomputed to match.
+    /// The transaction the cut falls in keeps only the patches before it.
+    pub fn prefix(&self, patches: usize) -> Result<TestData, ValidationError> {
+        let mut remaining = patches;
+        let mut txns = vec![];
+        for txn in &self.txns {
+            if remaining == 0 { break; }
+            let take = txn.patches.len().min(remaining);
+            txns.push(TestTxn { patches: txn.patches[..take].to_vec() });
+            remaining -= take;
+        }
+        let end_content = replay(&self.start_content, txns.iter().flat_map(|txn| txn.patches.iter()), self.using_byte_positions)?;
+        Ok(TestData {
+            using_byte_positions: self.using_byte_positions,
+            start_content: self.start_content.clone(),
+            end_content,
+            txns,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{load_testing_data, TestData, TestPatch, TestTxn};
+    use super::ValidationError;
+
+    #[test]
+    fn validate_datasets() {
+        for name in ["automerge-paper", "rustcode", "sveltecomponent", "seph-blog1", "friendsforever_flat"] {
+            let data = load_testing_data(&format!("../benchmark_data/{name}.json.gz"));
+            assert_eq!(data.validate(), Ok(()), "{name}");
+            assert_eq!(data.chars_to_bytes().validate(), Ok(()), "{name}");
+            let data = load_testing_data(&format!("../benchmark_data/ascii_only/{name}.json.gz"));
+            assert_eq!(data.validate(), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn validate_errors() {
+        let data = |using_byte_positions, patches| TestData {
+            using_byte_positions,
+            start_content: "aツ".into(),
+            end_content: "aツb".into(),
+            txns: vec![TestTxn { patches }],
+        };
+
+        assert_eq!(data(false, vec![TestPatch(2, 0, "b".into())]).validate(), Ok(()));
+        assert_eq!(data(true, vec![TestPatch(4, 0, "b".into())]).validate(), Ok(()));
+        assert_eq!(data(false, vec![TestPatch(2, 0, "c".into())]).validate(), Err(ValidationError::EndContent));
+        assert_eq!(
+            data(false, vec![TestPatch(1, 2, "".into())]).validate(),
+            Err(ValidationError::OutOfBounds { patch: 0, pos: 1, del: 2, len: 2 })
+        );
+        assert_eq!(
+            data(true, vec![TestPatch(2, 0, "b".into())]).validate(),
+            Err(ValidationError::NotCharBoundary { patch: 0, pos: 2 })
+        );
+    }
+
+    #[test]
+    fn prefix() {
+        let data = TestData {
+            using_byte_positions: false,
+            start_content: "ab".into(),
+            end_content: "xaYcd".into(),
+            txns: vec![
+                TestTxn { patches: vec![TestPatch(0, 0, "x".into()), TestPatch(2, 1, "Y".into())] },
+                TestTxn { patches: vec![TestPatch(3, 0, "c".into()), TestPatch(4, 0, "d".into())] },
+            ],
+        };
+
+        let three = data.prefix(3).unwrap();
+        assert_eq!(three.len(), 3);
+        assert_eq!(three.txns.len(), 2);
+        assert_eq!(three.end_content, "xaYc");
+        assert_eq!(three.validate(), Ok(()));
+
+        assert_eq!(data.prefix(0).unwrap().end_content, "ab");
+        assert_eq!(data.prefix(10).unwrap(), data);
+    }
+
+    #[test]
+    fn insert_units() {
+        // A lone surrogate can't be loaded as an insert.
+        let json = r#"{"startContent": "", "endContent": "", "txns": [{"patches": [[0, 0, "\ud83d"]]}]}"#;
+        assert!(serde_json::from_str::<TestData>(json).is_err());
+        let json = r#"{"startContent": "", "endContent": "😀", "txns": [{"patches": [[0, 0, "\ud83d\ude00"]]}]}"#;
+        assert_eq!(serde_json::from_str::<TestData>(json).unwrap().validate(), Ok(()));
+
+        // An insert landing inside a char inserted earlier would split it.
+        let data = |using_byte_positions, pos| TestData {
+            using_byte_positions,
+            start_content: "".into(),
+            end_content: "😀x".into(),
+            txns: vec![TestTxn { patches: vec![TestPatch(0, 0, "😀".into()), TestPatch(pos, 0, "x".into())] }],
+        };
+        assert_eq!(data(false, 1).validate(), Ok(()));
+        assert_eq!(data(true, 4).validate(), Ok(()));
+        assert_eq!(data(true, 2).validate(), Err(ValidationError::NotCharBoundary { patch: 1, pos: 2 }));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::results::{self, format_time, Record};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The groups which time the same operation over a range of sizes, and what the size measures.
+const SCALING_GROUPS: &[(&str, &str)] = &[
+    ("search_full", "text size (bytes)"),
+    ("build_string", "text size (bytes)"),
+    ("mc_cursor_count", "cursors"),
+    ("mc_cursor_size", "bytes between cursors"),
+];
+
+const COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+const MARKERS: &[char] = &['*', 'o', '+', 'x', '#', '@', '%', '&'];
+
+/// One rope's times across a scaling group, sorted by size.
+struct Series {
+    name: String,
+    points: Vec<(f64, f64)>,
+}
+
+/// Maps values onto 0..=1 on a log scale.
+struct LogScale {
+    min: f64,
+    max: f64,
+}
+
+impl LogScale {
+    fn new(values: impl Iterator<Item = f64>) -> Self {
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for v in values {
+            min = min.min(v.log10());
+            max = max.max(v.log10());
+        }
+        if max - min < 1e-9 {
+            min -= 0.5;
+            max += 0.5;
+        }
+        LogScale { min, max }
+    }
+
+    fn fraction(&self, value: f64) -> f64 {
+        (value.log10() - self.min) / (self.max - self.min)
+    }
+
+    /// Powers of `base` within the scale, skipping some so there are no more than eight.
+    fn ticks(&self, base: f64) -> Vec<f64> {
+        let lo = (self.min / base.log10()).ceil() as i32;
+        let hi = (self.max / base.log10()).floor() as i32;
+        let mut step = 1;
+        while (hi - lo) / step >= 8 {
+            step *= 2;
+        }
+        (lo..=hi)
+            .step_by(step as usize)
+            .map(|e| base.powi(e))
+            .collect()
+    }
+}
+
+fn series_for(records: &[Record], group: &str) -> Vec<Series> {
+    let mut series: Vec<Series> = vec![];
+    for r in records.iter().filter(|r| r.group == group) {
+        let Some(size) = r.parameter.as_deref().and_then(|p| p.parse::<f64>().ok()) else {
+            continue;
+        };
+        match series.iter_mut().find(|s| s.name == r.function) {
+            Some(s) => s.points.push((size, r.mean_ns)),
+            None => series.push(Series {
+                name: r.function.clone(),
+                points: vec![(size, r.mean_ns)],
+            }),
+        }
+    }
+    for s in &mut series {
+        s.points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    }
+    series
+}
+
+fn format_size(size: f64) -> String {
+    let (size, suffix) = match size {
+        s if s >= f64::powi(2.0, 30) => (s / f64::powi(2.0, 30), "Gi"),
+        s if s >= f64::powi(2.0, 20) => (s / f64::powi(2.0, 20), "Mi"),
+        s if s >= 1e4 => (s / 1024.0, "Ki"),
+        s => (s, ""),
+    };
+    let size = format!("{:.1}", size);
+    format!("{}{}", size.strip_suffix(".0").unwrap_or(&size), suffix)
+}
+
+fn scales(series: &[Series]) -> (LogScale, LogScale) {
+    let points = || series.iter().flat_map(|s| s.points.iter());
+    (
+        LogScale::new(points().map(|p| p.0)),
+        LogScale::new(points().map(|p| p.1)),
+    )
+}
+
+/// A log-log plot of size against time, one line per rope.
+fn svg_chart(group: &str, x_label: &str, series: &[Series]) -> String {
+    let (width, height) = (720.0, 440.0);
+    let (left, right, top, bottom) = (80.0, 150.0, 40.0, 50.0);
+    let (plot_w, plot_h) = (width - left - right, height - top - bottom);
+    let (xs, ys) = scales(series);
+    let x = |v: f64| format!("{:.1}", left + xs.fraction(v) * plot_w);
+    let y = |v: f64| format!("{:.1}", top + (1.0 - ys.fraction(v)) * plot_h);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="12">"#
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="24" text-anchor="middle" font-size="16">{group}</text>"#,
+        left + plot_w / 2.0
+    )
+    .unwrap();
+
+    for v in xs.ticks(2.0) {
+        writeln!(
+            svg,
+            r##"<line x1="{0}" y1="{top}" x2="{0}" y2="{1}" stroke="#ddd"/><text x="{0}" y="{2}" text-anchor="middle">{3}</text>"##,
+            x(v),
+            top + plot_h,
+            top + plot_h + 16.0,
+            format_size(v)
+        )
+        .unwrap();
+    }
+    for v in ys.ticks(10.0) {
+        writeln!(
+            svg,
+            r##"<line x1="{left}" y1="{0}" x2="{1}" y2="{0}" stroke="#ddd"/><text x="{2}" y="{0}" dy="4" text-anchor="end">{3}</text>"##,
+            y(v),
+            left + plot_w,
+            left - 6.0,
+            format_time(v)
+        )
+        .unwrap();
+    }
+    writeln!(
+        svg,
+        r#"<rect x="{left}" y="{top}" width="{plot_w}" height="{plot_h}" fill="none" stroke="black"/>"#
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle">{x_label}</text>"#,
+        left + plot_w / 2.0,
+        height - 12.0
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text transform="translate(16 {}) rotate(-90)" text-anchor="middle">mean time</text>"#,
+        top + plot_h / 2.0
+    )
+    .unwrap();
+
+    for (i, s) in series.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let points: Vec<String> = s
+            .points
+            .iter()
+            .map(|&(px, py)| format!("{},{}", x(px), y(py)))
+            .collect();
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="2"/>"#,
+            points.join(" ")
+        )
+        .unwrap();
+        for &(px, py) in &s.points {
+            writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="3" fill="{color}"/>"#,
+                x(px),
+                y(py)
+            )
+            .unwrap();
+        }
+        let legend_y = top + 10.0 + 20.0 * i as f64;
+        writeln!(
+            svg,
+            r#"<line x1="{0}" y1="{legend_y}" x2="{1}" y2="{legend_y}" stroke="{color}" stroke-width="2"/><text x="{2}" y="{3}">{4}</text>"#,
+            left + plot_w + 12.0,
+            left + plot_w + 32.0,
+            left + plot_w + 38.0,
+            legend_y + 4.0,
+            s.name
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// The same plot drawn with characters, for a quick look in the terminal.
+fn ascii_chart(group: &str, x_label: &str, series: &[Series]) -> String {
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 20;
+    let (xs, ys) = scales(series);
+    let mut grid = vec![vec![' '; WIDTH]; HEIGHT];
+    for (i, s) in series.iter().enumerate() {
+        for &(px, py) in &s.points {
+            let col = (xs.fraction(px) * (WIDTH - 1) as f64).round() as usize;
+            let row = ((1.0 - ys.fraction(py)) * (HEIGHT - 1) as f64).round() as usize;
+            grid[row][col] = MARKERS[i % MARKERS.len()];
+        }
+    }
+
+    let top = format_time(10f64.powf(ys.max));
+    let bottom = format_time(10f64.powf(ys.min));
+    let margin = top.len().max(bottom.len());
+    let mut chart = format!("{} (log-log)\n", group);
+    for (i, row) in grid.iter().enumerate() {
+        let label = match i {
+            0 => &top,
+            i if i == HEIGHT - 1 => &bottom,
+            _ => "",
+        };
+        let row: String = row.iter().collect();
+        writeln!(chart, "{:>margin$} |{}", label, row).unwrap();
+    }
+    writeln!(chart, "{:>margin$} +{}", "", "-".repeat(WIDTH)).unwrap();
+    let (first, last) = (
+        format_size(10f64.powf(xs.min).round()),
+        format_size(10f64.powf(xs.max).round()),
+    );
+    writeln!(
+        chart,
+        "{:>margin$}  {}{:>width$}",
+        "",
+        first,
+        last,
+        width = WIDTH - first.len()
+    )
+    .unwrap();
+    writeln!(chart, "{:>margin$}  {}", "", x_label).unwrap();
+    for (i, s) in series.iter().enumerate() {
+        writeln!(chart, "  {} {}", MARKERS[i % MARKERS.len()], s.name).unwrap();
+    }
+    chart
+}
+
+/// Plot each scaling group in the results written by `export`, as `<group>.svg` next to them and
+/// as a chart in the terminal. Groups run on another corpus (e.g. `search_full_cjk`) are plotted
+/// too.
+///
+/// Usage: report [results dir, default results]
+pub fn report(args: &[String]) {
+    let dir = match args {
+        [] => Path::new(env!("CARGO_MANIFEST_DIR")).join("results"),
+        [dir] => PathBuf::from(dir),
+        _ => panic!("Usage: report [results dir]"),
+    };
+    let records = results::load(&dir.join("results.json"));
+
+    let mut groups: Vec<&str> = records.iter().map(|r| r.group.as_str()).collect();
+    groups.dedup();
+    for group in groups {
+        let Some((_, x_label)) = SCALING_GROUPS
+            .iter()
+            .find(|(name, _)| group == *name || group.starts_with(&format!("{}_", name)))
+        else {
+            continue;
+        };
+        let series = series_for(&records, group);
+        if series.is_empty() {
+            continue;
+        }
+        let path = dir.join(format!("{}.svg", group));
+        fs::write(&path, svg_chart(group, x_label, &series)).unwrap();
+        println!("{}", ascii_chart(group, x_label, &series));
+        println!("Wrote {}\n", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(group: &str, function: &str, parameter: &str, mean_ns: f64) -> Record {
+        Record {
+            group: group.into(),
+            function: function.into(),
+            rope: function.into(),
+            parameter: Some(parameter.into()),
+            mean_ns,
+            ci_lower_ns: mean_ns,
+            ci_upper_ns: mean_ns,
+            throughput: None,
+            measured: 0,
+        }
+    }
+
+    /// Crop getting slower with size and Ropey faster, between 1KiB and 1MiB and 1µs and 1ms.
+    fn crossing() -> Vec<Series> {
+        let records = [
+            record("search_full", "crop", "1048576", 1e6),
+            record("search_full", "crop", "1024", 1e3),
+            record("search_full", "ropey", "1024", 1e6),
+            record("search_full", "ropey", "1048576", 1e3),
+        ];
+        series_for(&records, "search_full")
+    }
+
+    #[test]
+    fn log_scale() {
+        let scale = LogScale::new([10.0, 1000.0].iter().copied());
+        assert_eq!(scale.fraction(10.0), 0.0);
+        assert_eq!(scale.fraction(100.0), 0.5);
+        assert_eq!(scale.fraction(1000.0), 1.0);
+        assert_eq!(scale.ticks(10.0), [10.0, 100.0, 1000.0]);
+
+        // Too many powers of two, so only every fourth is kept.
+        let scale = LogScale::new([1.0, f64::powi(2.0, 20)].iter().copied());
+        assert_eq!(
+            scale.ticks(2.0),
+            [0, 4, 8, 12, 16, 20].map(|e| f64::powi(2.0, e))
+        );
+
+        // A single value sits in the middle.
+        assert_eq!(
+            LogScale::new([1000.0].iter().copied()).fraction(1000.0),
+            0.5
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(100.0), "100");
+        assert_eq!(format_size(1024.0), "1024");
+        assert_eq!(format_size(16384.0), "16Ki");
+        assert_eq!(format_size(f64::powi(2.0, 20)), "1Mi");
+        assert_eq!(format_size(1.5 * f64::powi(2.0, 30)), "1.5Gi");
+    }
+
+    #[test]
+    fn series() {
+        let records = [
+            record("search_full", "crop", "2048", 2.0),
+            record("search_full", "crop", "1024", 1.0),
+            record("search_full", "crop", "big", 3.0),
+            record("build_string", "crop", "1024", 4.0),
+            record("search_full", "ropey", "1024", 5.0),
+        ];
+        let series = series_for(&records, "search_full");
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].name, "crop");
+        assert_eq!(series[0].points, [(1024.0, 1.0), (2048.0, 2.0)]);
+        assert_eq!(series[1].name, "ropey");
+        assert_eq!(series[1].points, [(1024.0, 5.0)]);
+    }
+
+    #[test]
+    fn svg() {
+        let svg = svg_chart("search_full", "text size (bytes)", &crossing());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">search_full</text>"));
+        assert!(svg.contains(">text size (bytes)</text>"));
+        // The corners of the plot are at x 80 and 570, y 40 and 390.
+        assert!(svg.contains(
+            r##"<polyline points="80.0,390.0 570.0,40.0" fill="none" stroke="#1f77b4""##
+        ));
+        assert!(svg.contains(
+            r##"<polyline points="80.0,40.0 570.0,390.0" fill="none" stroke="#ff7f0e""##
+        ));
+        assert_eq!(svg.matches("<circle ").count(), 4);
+        assert!(svg.contains(">crop</text>") && svg.contains(">ropey</text>"));
+        // Six powers of two from 1KiB to 1MiB, and four powers of ten from 1µs to 1ms.
+        assert_eq!(svg.matches(r##"stroke="#ddd""##).count(), 10);
+        assert!(svg.contains(">1Mi</text>") && svg.contains(">1.00 ms</text>"));
+    }
+
+    #[test]
+    fn ascii() {
+        let chart = ascii_chart("search_full", "text size (bytes)", &crossing());
+        let lines: Vec<&str> = chart.lines().collect();
+        let blank = " ".repeat(62);
+        assert_eq!(lines.len(), 1 + 20 + 3 + 2);
+        assert_eq!(lines[0], "search_full (log-log)");
+        assert_eq!(lines[1], format!(" 1.00 ms |o{blank}*"));
+        assert_eq!(lines[2], format!("{:8} |{:64}", "", ""));
+        assert_eq!(lines[20], format!(" 1.00 µs |*{blank}o"));
+        assert_eq!(lines[21], format!("{:8} +{}", "", "-".repeat(64)));
+        assert_eq!(lines[22], format!("{:8}  1024{:>60}", "", "1Mi"));
+        assert_eq!(lines[23], format!("{:8}  text size (bytes)", ""));
+        assert_eq!(lines[24..], ["  * crop", "  o ropey"]);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{gen_realworld_text, gen_unicode_text, SplitMix64};
+
+/// How much of each kind of text to generate, relative to each other.
+#[derive(Clone, Copy)]
+pub struct Mix {
+    /// ASCII letters.
+    pub one_byte: u32,
+    /// Accented Latin letters, Greek and Cyrillic.
+    pub two_byte: u32,
+    /// CJK ideographs, kana and Hangul syllables.
+    pub three_byte: u32,
+    /// Emoji, maths letters and Gothic, all outside the BMP.
+    pub four_byte: u32,
+    /// An ASCII letter followed by one to three combining marks.
+    pub combining: u32,
+    /// Emoji ZWJ sequences, such as families and flags.
+    pub zwj: u32,
+}
+
+const ASCII: &[(char, char)] = &[('a', 'z')];
+const TWO_BYTE: &[(char, char)] = &[('à', 'ÿ'), ('α', 'ω'), ('а', 'я')];
+const THREE_BYTE: &[(char, char)] = &[('一', '龥'), ('ぁ', 'ゖ'), ('가', '힣')];
+const FOUR_BYTE: &[(char, char)] = &[('😀', '🙏'), ('𝐀', '𝐳'), ('𐌰', '𐍊')];
+const COMBINING_MARKS: &[(char, char)] = &[('\u{300}', '\u{36F}')];
+const ZWJ_SEQUENCES: &[&str] = &[
+    "👨\u{200D}👩\u{200D}👧\u{200D}👦",
+    "🏳\u{FE0F}\u{200D}🌈",
+    "🧑\u{1F3FD}\u{200D}💻",
+    "👩\u{200D}❤\u{FE0F}\u{200D}👨",
+    "🏃\u{1F3FF}\u{200D}♀\u{FE0F}",
+];
+
+fn random_char(rng: &mut SplitMix64, ranges: &[(char, char)]) -> char {
+    let (lo, hi) = ranges[rng.next() as usize % ranges.len()];
+    let offset = rng.next() % (hi as u64 - lo as u64 + 1);
+    char::from_u32(lo as u32 + offset as u32).unwrap()
+}
+
+impl Mix {
+    /// Generate up to `size` bytes of words made of one to eight pieces each, picked at random in
+    /// proportion to the mix. Words are separated by spaces, with a newline every 80 bytes or so.
+    pub fn generate(&self, size: usize) -> String {
+        let weights = [
+            self.one_byte,
+            self.two_byte,
+            self.three_byte,
+            self.four_byte,
+            self.combining,
+            self.zwj,
+        ];
+        let total: u32 = weights.iter().sum();
+        assert!(total > 0, "The mix is empty");
+
+        let mut rng = SplitMix64(size as u64);
+        let mut text = String::with_capacity(size);
+        let mut word = String::new();
+        let mut line_start = 0;
+        loop {
+            word.clear();
+            for _ in 0..1 + rng.next() % 8 {
+                let mut pick = (rng.next() % total as u64) as u32;
+                let mut kind = 0;
+                while pick >= weights[kind] {
+                    pick -= weights[kind];
+                    kind += 1;
+                }
+                match kind {
+                    0 => word.push(random_char(&mut rng, ASCII)),
+                    1 => word.push(random_char(&mut rng, TWO_BYTE)),
+                    2 => word.push(random_char(&mut rng, THREE_BYTE)),
+                    3 => word.push(random_char(&mut rng, FOUR_BYTE)),
+                    4 => {
+                        word.push(random_char(&mut rng, ASCII));
+                        for _ in 0..1 + rng.next() % 3 {
+                            word.push(random_char(&mut rng, COMBINING_MARKS));
+                        }
+                    }
+                    _ => word.push_str(ZWJ_SEQUENCES[rng.next() as usize % ZWJ_SEQUENCES.len()]),
+                }
+            }
+            if text.len() + word.len() + 1 > size {
+                return text;
+            }
+            text.push_str(&word);
+            if text.len() - line_start >= 80 {
+                text.push('\n');
+                line_start = text.len();
+            } else {
+                text.push(' ');
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Source {
+    /// `data/realworld.txt`, a LaTeX paper which is almost all ASCII.
+    Realworld,
+    /// `data/unicode.txt`, chat messages in many scripts with plenty of emoji.
+    Unicode,
+    /// The same string over and over.
+    Repeat(&'static str),
+    Generated(Mix),
+}
+
+pub const CORPORA: &[(&str, Source)] = &[
+    ("realworld", Source::Realworld),
+    ("unicode", Source::Unicode),
+    ("accents", Source::Repeat("à")),
+    (
+        "latin",
+        Source::Generated(Mix {
+            one_byte: 85,
+            two_byte: 10,
+            three_byte: 0,
+            four_byte: 0,
+            combining: 5,
+            zwj: 0,
+        }),
+    ),
+    (
+        "two_byte",
+        Source::Generated(Mix {
+            one_byte: 10,
+            two_byte: 90,
+            three_byte: 0,
+            four_byte: 0,
+            combining: 0,
+            zwj: 0,
+        }),
+    ),
+    (
+        "cjk",
+        Source::Generated(Mix {
+            one_byte: 10,
+            two_byte: 0,
+            three_byte: 90,
+            four_byte: 0,
+            combining: 0,
+            zwj: 0,
+        }),
+    ),
+    (
+        "emoji",
+        Source::Generated(Mix {
+            one_byte: 60,
+            two_byte: 0,
+            three_byte: 0,
+            four_byte: 25,
+            combining: 0,
+            zwj: 15,
+        }),
+    ),
+    (
+        "mixed",
+        Source::Generated(Mix {
+            one_byte: 40,
+            two_byte: 15,
+            three_byte: 25,
+            four_byte: 5,
+            combining: 10,
+            zwj: 5,
+        }),
+    ),
+];
+
+/// The text a size-parameterised benchmark runs on, chosen with the `BENCH_CORPUS` environment
+/// variable (e.g. `BENCH_CORPUS=cjk cargo bench`). Besides the names in `CORPORA`, it can be a
+/// custom mix: `mix:<1-byte>,<2-byte>,<3-byte>,<4-byte>,<combining>,<zwj>`.
+pub struct Corpus {
+    pub name: String,
+    source: Source,
+    is_default: bool,
+}
+
+impl Corpus {
+    /// The corpus from `BENCH_CORPUS`, or the one called `default` if it isn't set.
+    pub fn choose(default: &str) -> Self {
+        let name = std::env::var("BENCH_CORPUS").unwrap_or_else(|_| default.to_string());
+        let source = match name.strip_prefix("mix:") {
+            Some(spec) => Source::Generated(parse_mix(spec)),
+            None => match CORPORA.iter().find(|(n, _)| *n == name) {
+                Some((_, source)) => *source,
+                None => {
+                    let names: Vec<&str> = CORPORA.iter().map(|(n, _)| *n).collect();
+                    panic!(
+                        "Unknown corpus {}, expected one of {:?} or mix:...",
+                        name, names
+                    );
+                }
+            },
+        };
+        Corpus {
+            name: name.replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+            is_default: name == default,
+            source,
+        }
+    }
+
+    /// The name for a benchmark group using this corpus. Unless it's the default, the corpus
+    /// name is added so results for different corpora are kept apart.
+    pub fn group(&self, group: &str) -> String {
+        if self.is_default {
+            group.to_string()
+        } else {
+            format!("{}_{}", group, self.name)
+        }
+    }
+
+    /// Exactly `size` bytes of text, padded with spaces if the last char doesn't fit.
+    pub fn text(&self, size: usize) -> String {
+        let mut text = match self.source {
+            Source::Realworld => gen_realworld_text(size),
+            Source::Unicode => gen_unicode_text(size),
+            Source::Repeat(s) => s.repeat(size / s.len()),
+            Source::Generated(mix) => mix.generate(size),
+        };
+        let padding = size - text.len();
+        text.push_str(&" ".repeat(padding));
+        text
+    }
+}
+
+fn parse_mix(spec: &str) -> Mix {
+    let parts: Vec<u32> = spec
+        .split(',')
+        .map(|n| n.trim().parse().expect("Mix proportions must be integers"))
+        .collect();
+    let [one_byte, two_byte, three_byte, four_byte, combining, zwj] = parts[..] else {
+        panic!(
+            "Expected six proportions: mix:<1-byte>,<2-byte>,<3-byte>,<4-byte>,<combining>,<zwj>"
+        );
+    };
+    Mix {
+        one_byte,
+        two_byte,
+        three_byte,
+        four_byte,
+        combining,
+        zwj,
+    }
+}
@@ -1,6 +1,11 @@
 use crdt_testdata::*;
 use criterion::*;
+mod charts;
+mod corpus;
+mod memory;
+mod results;
 mod rope;
+use self::corpus::Corpus;
 use self::rope::*;
 use crop::Rope as Crop;
 use get_size::GetSize;
@@ -11,12 +16,17 @@ use std::any::type_name;
 use std::{
     borrow::Cow,
     fs::File,
-    io::{BufReader, Read},
+    io::{self, BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 use text_buffer::Buffer;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
 
 impl Rope for JumpRope {
     const NAME: &'static str = "JumpRope";
+    const INDEXES_LINES: bool = true;
 
     #[inline(always)]
     fn new() -> Self {
@@ -54,10 +64,65 @@ impl Rope for JumpRope {
     fn byte_len(&self) -> usize {
         self.len_bytes()
     }
+
+    fn apply_batch(&mut self, edits: &[TestPatch]) {
+        rebuild_from_string(self, edits);
+    }
+
+    fn for_each_chunk(&self, range: Range<usize>, mut f: impl FnMut(&str) -> bool) {
+        for chunk in self.slice_substrings(range) {
+            if !f(chunk) {
+                break;
+            }
+        }
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.char_to_line(pos)
+    }
+
+    fn line_to_offset(&self, line: usize) -> usize {
+        self.line_to_char(line.min(self.len_lines()))
+    }
+
+    // JumpRope indexes lines, chars and (with wchar_conversion) UTF-16 code units, but not bytes,
+    // so byte columns are counted along the line.
+    fn offset_to_line_col(&self, pos: usize, unit: Unit) -> (usize, usize) {
+        let line = self.char_to_line(pos);
+        let start = self.line_to_char(line);
+        match unit {
+            Unit::Char => (line, pos - start),
+            Unit::Utf16 => (
+                line,
+                self.chars_to_wchars(pos) - self.chars_to_wchars(start),
+            ),
+            Unit::Byte => offset_to_line_col_by_chunks(self, pos, unit),
+        }
+    }
+
+    fn line_col_to_offset(&self, line: usize, col: usize, unit: Unit) -> usize {
+        let start = self.line_to_char(line);
+        match unit {
+            Unit::Char => start + col,
+            Unit::Utf16 => {
+                let wchars = self.chars_to_wchars(start) + col;
+                let pos = self.wchars_to_chars(wchars);
+                // Step back off a char whose surrogate pair `col` splits.
+                if self.chars_to_wchars(pos) > wchars {
+                    pos - 1
+                } else {
+                    pos
+                }
+            }
+            Unit::Byte => line_col_to_offset_by_chunks(self, line, col, unit),
+        }
+    }
 }
 
 impl Rope for Ropey {
     const NAME: &'static str = "Ropey";
+    const INDEXES_LINES: bool = true;
+    const INDEXES_CHAR_BYTE: bool = true;
 
     #[inline(always)]
     fn new() -> Self {
@@ -115,11 +180,127 @@ impl Rope for Ropey {
     fn byte_len(&self) -> usize {
         self.len_bytes()
     }
+
+    fn from_chunks<'a>(chunks: impl Iterator<Item = &'a str>) -> Self {
+        let mut builder = ropey::RopeBuilder::new();
+        for chunk in chunks {
+            builder.append(chunk);
+        }
+        builder.finish()
+    }
+
+    fn from_reader(reader: impl Read) -> io::Result<Self> {
+        Ropey::from_reader(reader)
+    }
+
+    fn apply_batch(&mut self, edits: &[TestPatch]) {
+        match batch_spans::<Self>(edits) {
+            Some(spans) if worth_rebuilding(self, &spans) => {
+                let mut builder = ropey::RopeBuilder::new();
+                for_each_piece(self, &spans, |piece| builder.append(piece));
+                *self = builder.finish();
+            }
+            _ => apply_each(self, edits),
+        }
+    }
+
+    fn for_each_chunk(&self, range: Range<usize>, mut f: impl FnMut(&str) -> bool) {
+        for chunk in self.slice(range).chunks() {
+            if !f(chunk) {
+                break;
+            }
+        }
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.char_to_line(pos)
+    }
+
+    fn line_to_offset(&self, line: usize) -> usize {
+        self.line_to_char(line.min(self.len_lines()))
+    }
+
+    fn char_to_byte(&self, pos: usize) -> usize {
+        Ropey::char_to_byte(self, pos)
+    }
+
+    fn byte_to_char(&self, pos: usize) -> usize {
+        Ropey::byte_to_char(self, pos)
+    }
+
+    // With the cr_lines feature, "\r\n" is a single line break.
+    fn for_each_line(&self, mut f: impl FnMut(usize)) {
+        for line in self.lines() {
+            f(strip_line_break(line.len_bytes(), |i| line.byte(i)));
+        }
+    }
+
+    fn next_grapheme_boundary(&self, pos: usize) -> usize {
+        ropey_grapheme_boundary(self, pos, true)
+    }
+
+    fn prev_grapheme_boundary(&self, pos: usize) -> usize {
+        ropey_grapheme_boundary(self, pos, false)
+    }
+
+    fn offset_to_line_col(&self, pos: usize, unit: Unit) -> (usize, usize) {
+        let line = self.char_to_line(pos);
+        let start = self.line_to_char(line);
+        let col = match unit {
+            Unit::Char => pos - start,
+            Unit::Byte => Ropey::char_to_byte(self, pos) - Ropey::char_to_byte(self, start),
+            Unit::Utf16 => self.char_to_utf16_cu(pos) - self.char_to_utf16_cu(start),
+        };
+        (line, col)
+    }
+
+    fn line_col_to_offset(&self, line: usize, col: usize, unit: Unit) -> usize {
+        let start = self.line_to_char(line);
+        match unit {
+            Unit::Char => start + col,
+            Unit::Byte => Ropey::byte_to_char(self, Ropey::char_to_byte(self, start) + col),
+            Unit::Utf16 => self.utf16_cu_to_char(self.char_to_utf16_cu(start) + col),
+        }
+    }
+}
+
+/// Feed Ropey's chunks straight to a `GraphemeCursor`, as in Ropey's documentation, rather than
+/// copying them into a window.
+fn ropey_grapheme_boundary(r: &Ropey, pos: usize, forward: bool) -> usize {
+    let byte = Ropey::char_to_byte(r, pos);
+    let (mut chunk, mut chunk_start, _, _) = r.chunk_at_byte(byte);
+    let mut cursor = GraphemeCursor::new(byte, r.len_bytes(), true);
+    loop {
+        let boundary = if forward {
+            cursor.next_boundary(chunk, chunk_start)
+        } else {
+            cursor.prev_boundary(chunk, chunk_start)
+        };
+        match boundary {
+            Ok(Some(b)) => return Ropey::byte_to_char(r, b),
+            Ok(None) => return if forward { r.len_chars() } else { 0 },
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_start += chunk.len();
+                chunk = r.chunk_at_byte(chunk_start).0;
+            }
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (prev, prev_start, _, _) = r.chunk_at_byte(chunk_start - 1);
+                chunk = prev;
+                chunk_start = prev_start;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let (context, context_start, _, _) = r.chunk_at_byte(n - 1);
+                cursor.provide_context(context, context_start);
+            }
+            Err(GraphemeIncomplete::InvalidOffset) => unreachable!(),
+        }
+    }
 }
 
 impl Rope for Crop {
     const NAME: &'static str = "Crop";
     const EDITS_USE_BYTE_OFFSETS: bool = true;
+    const INDEXES_LINES: bool = true;
 
     fn new() -> Self {
         Self::new()
@@ -172,9 +353,103 @@ impl Rope for Crop {
     fn byte_len(&self) -> usize {
         self.byte_len()
     }
+
+    fn from_chunks<'a>(chunks: impl Iterator<Item = &'a str>) -> Self {
+        let mut builder = crop::RopeBuilder::new();
+        for chunk in chunks {
+            builder.append(chunk);
+        }
+        builder.build()
+    }
+
+    fn from_reader(reader: impl Read) -> io::Result<Self> {
+        let mut builder = crop::RopeBuilder::new();
+        read_chunks(reader, |chunk| {
+            builder.append(chunk);
+        })?;
+        Ok(builder.build())
+    }
+
+    fn apply_batch(&mut self, edits: &[TestPatch]) {
+        match batch_spans::<Self>(edits) {
+            Some(spans) if worth_rebuilding(self, &spans) => {
+                let mut builder = crop::RopeBuilder::new();
+                for_each_piece(self, &spans, |piece| {
+                    builder.append(piece);
+                });
+                *self = builder.build();
+            }
+            _ => apply_each(self, edits),
+        }
+    }
+
+    fn for_each_chunk(&self, range: Range<usize>, mut f: impl FnMut(&str) -> bool) {
+        for chunk in self.byte_slice(range).chunks() {
+            if !f(chunk) {
+                break;
+            }
+        }
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.line_of_byte(pos)
+    }
+
+    fn line_to_offset(&self, line: usize) -> usize {
+        self.byte_of_line(line.min(self.line_len()))
+    }
+
+    fn floor_char_boundary(&self, mut pos: usize) -> usize {
+        while !self.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    // Crop only indexes lines by byte, so other units have to be counted.
+    fn offset_to_line_col(&self, pos: usize, unit: Unit) -> (usize, usize) {
+        match unit {
+            Unit::Byte => {
+                let line = self.line_of_byte(pos);
+                (line, pos - self.byte_of_line(line))
+            }
+            _ => offset_to_line_col_by_chunks(self, pos, unit),
+        }
+    }
+
+    fn line_col_to_offset(&self, line: usize, col: usize, unit: Unit) -> usize {
+        match unit {
+            Unit::Byte => self.floor_char_boundary(self.byte_of_line(line) + col),
+            _ => line_col_to_offset_by_chunks(self, line, col, unit),
+        }
+    }
+
+    // `lines` strips both "\n" and "\r\n".
+    fn for_each_line(&self, mut f: impl FnMut(usize)) {
+        for line in self.lines() {
+            f(line.byte_len());
+        }
+        crop_trailing_line(self, f);
+    }
+}
+
+/// `for_each_line` for Crop using `raw_lines`, which keeps the line breaks, instead of `lines`.
+fn crop_raw_lines(r: &Crop, mut f: impl FnMut(usize)) {
+    for line in r.raw_lines() {
+        f(strip_line_break(line.byte_len(), |i| line.byte(i)));
+    }
+    crop_trailing_line(r, f);
+}
+
+/// Crop doesn't count the empty line after a final line break.
+fn crop_trailing_line(r: &Crop, mut f: impl FnMut(usize)) {
+    if r.byte_len() == 0 || r.byte(r.byte_len() - 1) == b'\n' {
+        f(0);
+    }
 }
 impl Rope for Buffer {
     const NAME: &'static str = "Buffer";
+    const INDEXES_CHAR_BYTE: bool = true;
 
     #[inline(always)]
     fn new() -> Self {
@@ -220,12 +495,72 @@ impl Rope for Buffer {
     fn byte_len(&self) -> usize {
         self.len()
     }
+
+    // Applying the spans from last to first leaves the positions of the ones still to come alone,
+    // so the gap sweeps across the text once, towards the start, rather than being rebuilt.
+    fn apply_batch(&mut self, edits: &[TestPatch]) {
+        match batch_spans::<Self>(edits) {
+            Some(spans) => {
+                for (pos, del, ins) in spans.into_iter().rev() {
+                    self.edit_at(pos, del, ins);
+                }
+            }
+            None => apply_each(self, edits),
+        }
+    }
+
+    fn for_each_chunk(&self, range: Range<usize>, mut f: impl FnMut(&str) -> bool) {
+        f(&self.read(range));
+    }
+
+    fn char_to_byte(&self, pos: usize) -> usize {
+        Buffer::char_to_byte(self, pos)
+    }
+
+    // The buffer only converts chars to bytes, so search for the last char starting at or before
+    // `pos`. A char is 1 to 4 bytes, which bounds where it can be.
+    fn byte_to_char(&self, pos: usize) -> usize {
+        let (mut lo, mut hi) = (pos / 4, pos.min(self.len_chars()));
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if Buffer::char_to_byte(self, mid) <= pos {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        lo
+    }
+
+    // Columns are worked out with the buffer's char index.
+    fn offset_to_line_col(&self, pos: usize, unit: Unit) -> (usize, usize) {
+        let line = self.line_of(pos);
+        let start = self.line_to_offset(line);
+        match unit {
+            Unit::Char => (line, pos - start),
+            Unit::Byte => (
+                line,
+                Buffer::char_to_byte(self, pos) - Buffer::char_to_byte(self, start),
+            ),
+            Unit::Utf16 => offset_to_line_col_by_chunks(self, pos, unit),
+        }
+    }
+
+    fn line_col_to_offset(&self, line: usize, col: usize, unit: Unit) -> usize {
+        let start = self.line_to_offset(line);
+        match unit {
+            Unit::Char => start + col,
+            Unit::Byte => self.byte_to_char(Buffer::char_to_byte(self, start) + col),
+            Unit::Utf16 => line_col_to_offset_by_chunks(self, line, col, unit),
+        }
+    }
 }
 
 use criterion::measurement::WallTime;
 
-fn gen_realworld_text(size: usize) -> String {
-    let filename = format!("{}/data/realworld.txt", env!("CARGO_MANIFEST_DIR"));
+/// Repeat a file from *data* up to `size` bytes, or a little under to end on a char boundary.
+fn gen_text(name: &str, size: usize) -> String {
+    let filename = format!("{}/data/{name}", env!("CARGO_MANIFEST_DIR"));
     // read the file into a string
     let file = File::open(filename).unwrap();
     let mut reader = BufReader::new(file);
@@ -233,15 +568,59 @@ fn gen_realworld_text(size: usize) -> String {
     reader.read_to_string(&mut contents).unwrap();
     let repeat = size / contents.len();
     let mut string = contents.repeat(repeat);
-    string.push_str(&contents[..size % contents.len()]);
+    let mut rest = size % contents.len();
+    while !contents.is_char_boundary(rest) {
+        rest -= 1;
+    }
+    string.push_str(&contents[..rest]);
     string
 }
 
-fn append<R: Rope + for<'a> From<&'a str>>(b: &mut Bencher) {
+fn gen_realworld_text(size: usize) -> String {
+    gen_text("realworld.txt", size)
+}
+
+/// Short chat messages mixing emoji sequences, combining marks, CJK and other scripts.
+fn gen_unicode_text(size: usize) -> String {
+    gen_text("unicode.txt", size)
+}
+
+/// Whether this is a `--quick` run, which only checks that every benchmark works.
+fn quick() -> bool {
+    std::env::var_os("BENCH_QUICK").is_some()
+}
+
+/// Whether criterion is going to measure the benchmarks and write their results. Without
+/// `--bench` (which `cargo bench` passes), or with `--test` or `--list`, it only runs each one
+/// once or lists them.
+fn measuring(args: &[String]) -> bool {
+    let has = |flag: &str| args.iter().any(|arg| arg == flag);
+    has("--bench") && !has("--test") && !has("--list")
+}
+
+/// The size to benchmark instead of `size`: the same, or 256 times smaller in a quick run.
+fn scaled(size: usize) -> usize {
+    if quick() {
+        size >> 8
+    } else {
+        size
+    }
+}
+
+/// `scaled` for a list of increasing counts, such as cursors, which have to stay at least 1.
+/// Counts which scale to the same value are only run once.
+fn scaled_counts(counts: &[usize]) -> Vec<usize> {
+    let mut scaled: Vec<usize> = counts.iter().map(|&n| scaled(n).max(1)).collect();
+    scaled.dedup();
+    scaled
+}
+
+const APPEND_TEXT: &str = "ropes";
+const APPEND_COUNT: usize = usize::pow(2, 20) / APPEND_TEXT.len();
+
+fn append<R: Rope + for<'a> From<&'a str>>(b: &mut Bencher, count: usize) {
     let mut r = R::new();
-    let target = usize::pow(2, 20);
-    let text = "ropes";
-    let count = target / text.len();
+    let text = APPEND_TEXT;
     let mut len = 0;
     b.iter(|| {
         for _ in 0..count {
@@ -329,8 +708,71 @@ fn mc_naive<R: Rope + for<'a> From<&'a str>>(
     });
 }
 
-fn search_linewise<R: Rope + From<String>>(b: &mut Bencher) {
-    let contents = gen_realworld_text(usize::pow(2, 20));
+/// The edits made by `mc_smart`, as one batch per keystroke (each cursor inserting a character),
+/// followed by a batch deleting everything that was inserted.
+fn mc_smart_batches(params: &(usize, usize, usize, usize)) -> Vec<Vec<TestPatch>> {
+    let cursors = params.1;
+    let step = params.2;
+    let width = params.3;
+    let text = "b";
+    let l = text.len();
+    let mut batches = Vec::with_capacity(width + 1);
+    for mc in 0..width {
+        let batch = (0..cursors)
+            .map(|i| {
+                let idx = if is_even(mc) {
+                    (i * (step + ((mc + 1) * l))) + mc * l
+                } else {
+                    let i = cursors - 1 - i;
+                    (i * (step + (mc * l))) + mc * l
+                };
+                TestPatch(idx, 0, text.to_string())
+            })
+            .collect();
+        batches.push(batch);
+    }
+    let len = cursors * width * l;
+    if is_even(width) {
+        batches.push(vec![TestPatch((width - 1) * l, len, String::new())]);
+    } else {
+        let idx = ((cursors - 1) * (step + (width * l))) + (width - 1) * l;
+        batches.push(vec![TestPatch(idx - len, len, String::new())]);
+    }
+    batches
+}
+
+fn mc_batch<R: Rope + for<'a> From<&'a str>>(
+    b: &mut Bencher,
+    params: &(usize, usize, usize, usize),
+) {
+    let batches = mc_smart_batches(params);
+    let init = "a".repeat(params.0);
+    let mut container = R::from(&*init);
+    b.iter(|| {
+        let orig_len = container.byte_len();
+        for batch in &batches {
+            container.apply_batch(batch);
+        }
+        assert_eq!(container.byte_len(), orig_len);
+    });
+}
+
+/// Compare applying multi-cursor edits in naive order, smart order, and batched per keystroke,
+/// under the function names `[naive, smart, batch]`.
+fn mc_compare<R: Rope + for<'a> From<&'a str>>(
+    group: &mut BenchmarkGroup<WallTime>,
+    [naive, smart, batch]: [&str; 3],
+    d: &str,
+    params: &(usize, usize, usize, usize),
+) {
+    use BenchmarkId as id;
+    group.bench_function(id::new(naive, d), |b| mc_naive::<R>(b, params));
+    group.bench_function(id::new(smart, d), |b| mc_smart::<R>(b, params));
+    group.bench_function(id::new(batch, d), |b| mc_batch::<R>(b, params));
+}
+
+fn search_linewise<R: Rope + From<String>>(b: &mut Bencher, corpus: &Corpus, size: usize) {
+    let contents = corpus.text(size);
 
     let r = R::from(contents);
 
@@ -362,24 +804,104 @@ fn search_full<R: Rope + for<'a> From<&'a str>>(b: &mut Bencher, text: &str) {
     });
 }
 
-fn move_gap<R: Rope + for<'a> From<&'a str>>(b: &mut Bencher, text: &str) {
-    let len = text.len();
+/// Edit alternately at two positions `distance` apart (in bytes for ASCII text, otherwise the
+/// rope's own units), so that every edit has to move the cursor (or, for `Buffer`, the gap) that
+/// far.
+fn move_gap<R: Rope + for<'a> From<&'a str>>(b: &mut Bencher, text: &str, distance: usize) {
     let mut container = R::from(text);
+    let len = container.char_len();
+    let distance = distance.min(len);
+    let start = container.floor_char_boundary((len - distance) / 2);
+    let end = container.floor_char_boundary(start + distance);
     let mut forward = true;
     b.iter(|| {
-        if forward {
-            container.insert_at(len / 2, "b");
-            container.del_at(len / 2, 1);
-        } else {
-            container.insert_at(0, "b");
-            container.del_at(0, 1);
-        }
+        let pos = if forward { end } else { start };
+        container.insert_at(pos, "b");
+        container.del_at(pos, 1);
         forward = !forward;
     });
 }
 
-fn build_string<R: Rope + From<String>>(b: &mut Bencher, size: &usize) {
-    let contents = gen_realworld_text(*size);
+/// A small deterministic PRNG (SplitMix64), so every rope sees the same edit positions.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A float in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// How edit positions are spread over the document.
+#[derive(Clone, Copy)]
+enum Locality {
+    /// Anywhere in the document.
+    Uniform,
+    /// Zipf-like (s = 1): offset `k` is chosen with probability proportional to `1 / k`, so a
+    /// few positions near the start are very hot.
+    Zipf,
+    /// Each edit is within the given distance of the previous one.
+    RandomWalk(usize),
+}
+
+impl Locality {
+    fn name(self) -> String {
+        match self {
+            Locality::Uniform => "uniform".to_string(),
+            Locality::Zipf => "zipf".to_string(),
+            Locality::RandomWalk(step) => format!("walk_{step}"),
+        }
+    }
+
+    /// Generate `count` edit positions in a document of length `len`.
+    fn positions(self, len: usize, count: usize) -> Vec<usize> {
+        let mut rng = SplitMix64(len as u64);
+        let mut pos = len / 2;
+        (0..count)
+            .map(|_| {
+                pos = match self {
+                    Locality::Uniform => (rng.next() % (len as u64 + 1)) as usize,
+                    // Inverse CDF of the continuous approximation.
+                    Locality::Zipf => ((len + 1) as f64).powf(rng.next_f64()) as usize - 1,
+                    Locality::RandomWalk(step) => {
+                        let delta = (rng.next() % (2 * step as u64 + 1)) as usize;
+                        (pos + delta).saturating_sub(step).min(len)
+                    }
+                };
+                pos
+            })
+            .collect()
+    }
+}
+
+const EDITS_PER_ITER: usize = 1000;
+
+/// Insert and immediately delete a character at each of a sequence of positions.
+fn random_edits<R: Rope + for<'a> From<&'a str>>(b: &mut Bencher, text: &str, locality: Locality) {
+    let mut container = R::from(text);
+    let positions: Vec<usize> = locality
+        .positions(container.char_len(), EDITS_PER_ITER)
+        .into_iter()
+        .map(|pos| container.floor_char_boundary(pos))
+        .collect();
+    b.iter(|| {
+        for &pos in &positions {
+            container.insert_at(pos, "b");
+            container.del_at(pos, 1);
+        }
+    });
+}
+
+fn build_string<R: Rope + From<String>>(b: &mut Bencher, size: &usize, corpus: &Corpus) {
+    let contents = corpus.text(*size);
     let r = R::from(contents);
 
     b.iter(|| {
@@ -391,13 +913,9 @@ fn build_from_edits<R: Rope>(size: usize) -> R {
     let test_data = load_named_data("automerge-paper");
     let len = test_data.end_content.len();
 
-    let mut r = R::new();
+    let mut r = replay_init(&test_data);
     for _ in 0..(size / len) {
-        for txn in &test_data.txns {
-            for TestPatch(pos, del, ins) in &txn.patches {
-                r.edit_at(*pos, *del, ins);
-            }
-        }
+        replay_edits(&mut r, &test_data);
     }
     r
 }
@@ -440,12 +958,17 @@ fn report_space_overhead_edits() {
 }
 
 fn bench_create(c: &mut Criterion) {
-    let mut group = c.benchmark_group("from_string");
+    let corpus = Corpus::choose("accents");
+    let name = corpus.group("from_string");
+    // The string, a clone (or the chunks concatenated) and the rope built from it.
+    let size = memory::shrink(&name, scaled(usize::pow(2, 30)), |size| {
+        memory::THREE_COPIES * size
+    });
+    let mut group = c.benchmark_group(name);
     group.sample_size(10);
 
-    let size = usize::pow(2, 30);
-    let string = "à".repeat(size / 2);
-    assert_eq!(string.len(), size);
+    let string = corpus.text(size);
+    group.throughput(Throughput::Bytes(size as u64));
 
     group.bench_function("clone", |b| b.iter(|| string.clone()));
     group.bench_function("buffer", |b| b.iter(|| Buffer::from(string.clone())));
@@ -454,20 +977,210 @@ fn bench_create(c: &mut Criterion) {
     group.bench_function("ropey", |b| b.iter(|| Ropey::from(string.clone())));
     group.finish();
 
-    let mut group = c.benchmark_group("from_str");
+    let mut group = c.benchmark_group(corpus.group("from_str"));
+    group.throughput(Throughput::Bytes(size as u64));
     group.bench_function("buffer", |b| b.iter(|| Buffer::from(&*string)));
     group.bench_function("crop", |b| b.iter(|| Crop::from(&*string)));
     group.bench_function("jumprope", |b| b.iter(|| JumpRope::from(&*string)));
     group.bench_function("ropey", |b| b.iter(|| Ropey::from(&*string)));
+
+    // Building from a stream of small chunks, as when reading from a socket or decompressor.
+    let chunks = split_chunks(&string, 1024);
+    group.bench_function("crop_builder", |b| {
+        b.iter(|| Crop::from_chunks(chunks.iter().copied()))
+    });
+    group.bench_function("ropey_builder", |b| {
+        b.iter(|| Ropey::from_chunks(chunks.iter().copied()))
+    });
+    group.bench_function("buffer_collect", |b| {
+        b.iter(|| Buffer::from(chunks.concat()))
+    });
+    group.bench_function("crop_collect", |b| b.iter(|| Crop::from(chunks.concat())));
+    group.bench_function("jumprope_collect", |b| {
+        b.iter(|| JumpRope::from(chunks.concat()))
+    });
+    group.bench_function("ropey_collect", |b| b.iter(|| Ropey::from(chunks.concat())));
     group.finish();
 }
 
+/// Split `text` into chunks of about `size` bytes, ending on char boundaries.
+fn split_chunks(text: &str, size: usize) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Write `size` bytes of the corpus to a file under `target/`, reusing it if it's already there.
+fn bench_file(corpus: &Corpus, size: usize) -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/bench_files");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}_{size}.txt", corpus.name));
+    if std::fs::metadata(&path).map(|m| m.len()).ok() != Some(size as u64) {
+        std::fs::write(&path, corpus.text(size)).unwrap();
+    }
+    path
+}
+
+fn open_file<R: Rope>(path: &Path) -> R {
+    R::from_reader(BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+fn save_file<R: Rope>(r: &R, path: &Path) {
+    let mut writer = BufWriter::new(File::create(path).unwrap());
+    r.write_to(&mut writer).unwrap();
+    writer.flush().unwrap();
+}
+
+const FILE_SIZES: [usize; 4] = [
+    usize::pow(2, 20),
+    usize::pow(2, 24),
+    usize::pow(2, 28),
+    usize::pow(2, 30),
+];
+
+fn file_pipeline<R: Rope>(c: &mut Criterion, name: &str) {
+    let corpus = Corpus::choose("realworld");
+    for size in FILE_SIZES.map(scaled) {
+        // The text written to the file if it isn't there yet, the rope and the text saved from
+        // it. Both groups are skipped together, so both are recorded.
+        let fits = ["file_open", "file_save"].map(|group| {
+            let parameter = format!("{name}/{size}");
+            memory::fits(&corpus.group(group), parameter, memory::THREE_COPIES * size)
+        });
+        if fits.contains(&false) {
+            continue;
+        }
+        let path = bench_file(&corpus, size);
+        let out = path.with_extension(format!("{name}.out"));
+
+        let mut group = c.benchmark_group(corpus.group("file_open"));
+        group.sample_size(10);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter_with_large_drop(|| open_file::<R>(&path))
+        });
+        group.finish();
+
+        let r = open_file::<R>(&path);
+        let mut group = c.benchmark_group(corpus.group("file_save"));
+        group.sample_size(10);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter(|| save_file(&r, &out))
+        });
+        group.finish();
+        // It isn't written if a filter skipped `file_save`.
+        let _ = std::fs::remove_file(&out);
+    }
+}
+
+fn bench_file_pipeline(c: &mut Criterion) {
+    file_pipeline::<Buffer>(c, "buffer");
+    file_pipeline::<Crop>(c, "crop");
+    file_pipeline::<JumpRope>(c, "jumprope");
+    file_pipeline::<Ropey>(c, "ropey");
+}
+
+/// Read a field (reported in kB) from `/proc/self/status`, returning it in bytes.
+fn proc_status(field: &str) -> usize {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let line = status.lines().find(|l| l.starts_with(field)).unwrap();
+    let kb: usize = line[field.len() + 1..]
+        .trim()
+        .trim_end_matches(" kB")
+        .parse()
+        .unwrap();
+    kb * 1024
+}
+
+/// Peak memory used while opening and saving a file, above what was in use beforehand. Linux only.
+fn file_peak_memory<R: Rope>(path: &Path, out: &Path) -> usize {
+    // Writing 5 resets the peak RSS (VmHWM) to the current RSS.
+    std::fs::write("/proc/self/clear_refs", "5").unwrap();
+    let base = proc_status("VmRSS");
+    let r = open_file::<R>(path);
+    save_file(&r, out);
+    drop(r);
+    proc_status("VmHWM") - base
+}
+
+/// Print the peak memory used by each rope to open and save files of each size, skipping the
+/// sizes which don't fit in the memory budget.
+///
+/// Each rope is measured in a fresh process, as the allocator keeps memory freed by one rope
+/// resident and would hide the next one's peak.
+///
+/// Usage: file-memory [<rope> <file> <out>]
+fn report_file_memory(args: &[String]) {
+    if let [rope, path, out] = args {
+        let (path, out) = (Path::new(path), Path::new(out));
+        let peak = match rope.as_str() {
+            "buffer" => file_peak_memory::<Buffer>(path, out),
+            "crop" => file_peak_memory::<Crop>(path, out),
+            "jumprope" => file_peak_memory::<JumpRope>(path, out),
+            "ropey" => file_peak_memory::<Ropey>(path, out),
+            _ => panic!("Unknown rope {}", rope),
+        };
+        println!("{}", peak);
+        return;
+    }
+
+    let corpus = Corpus::choose("realworld");
+    for size in FILE_SIZES {
+        // The text written to the file if it isn't there yet, the rope and the text saved from
+        // it.
+        if !memory::fits("file-memory", size, memory::THREE_COPIES * size) {
+            continue;
+        }
+        let path = bench_file(&corpus, size);
+        let out = path.with_extension("out");
+        let mb = |bytes: usize| bytes as f64 / usize::pow(2, 20) as f64;
+        println!("{} MB file:", mb(size));
+        for (name, rope) in [
+            ("Buffer", "buffer"),
+            ("Crop", "crop"),
+            ("JumpRope", "jumprope"),
+            ("Ropey", "ropey"),
+        ] {
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .arg("file-memory")
+                .arg(rope)
+                .arg(&path)
+                .arg(&out)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "Measuring {} failed", name);
+            let peak: usize = String::from_utf8(output.stdout)
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            println!("  {}: {:.1} MB", name, mb(peak));
+        }
+        std::fs::remove_file(&out).unwrap();
+    }
+}
+
 fn bench_save(c: &mut Criterion) {
-    let mut group = c.benchmark_group("save");
+    let corpus = Corpus::choose("accents");
+    let name = corpus.group("save");
+    // The string, all four ropes and the string saved from one of them.
+    let size = memory::shrink(&name, scaled(usize::pow(2, 30)), |size| {
+        memory::SIX_COPIES * size
+    });
+    let mut group = c.benchmark_group(name);
 
-    let size = usize::pow(2, 30);
-    let string = "à".repeat(size / 2);
-    assert_eq!(string.len(), size);
+    let string = corpus.text(size);
+    group.throughput(Throughput::Bytes(size as u64));
 
     let x = Buffer::from(string.clone());
     group.bench_function("buffer", |b| b.iter(|| ToString::to_string(&x)));
@@ -482,31 +1195,50 @@ fn bench_save(c: &mut Criterion) {
 
 fn bench_append(c: &mut Criterion) {
     let mut group = c.benchmark_group("append");
+    let count = scaled(APPEND_COUNT);
+    group.throughput(Throughput::Bytes((count * APPEND_TEXT.len()) as u64));
 
-    group.bench_function("buffer", append::<Buffer>);
-    group.bench_function("crop", append::<Crop>);
-    group.bench_function("jumprope", append::<JumpRope>);
-    group.bench_function("ropey", append::<Ropey>);
+    group.bench_function("buffer", |b| append::<Buffer>(b, count));
+    group.bench_function("crop", |b| append::<Crop>(b, count));
+    group.bench_function("jumprope", |b| append::<JumpRope>(b, count));
+    group.bench_function("ropey", |b| append::<Ropey>(b, count));
     group.finish();
 }
 
 fn bench_mc_smart(c: &mut Criterion) {
     let mut group = c.benchmark_group("mc_smart");
-    use BenchmarkId as id;
+    // Buffer keeps the `naive` and `smart` IDs from before this group compared the other ropes,
+    // so its history carries on.
+    let buffer = ["naive", "smart", "buffer/batch"];
+    let crop = ["crop/naive", "crop/smart", "crop/batch"];
+    let jumprope = ["jumprope/naive", "jumprope/smart", "jumprope/batch"];
+    let ropey = ["ropey/naive", "ropey/smart", "ropey/batch"];
 
     for step in [
         10, 50, 100, 250, 500, 1000, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 10000,
     ] {
-        let cursors = 1000;
+        let cursors = scaled_counts(&[1000])[0];
         let width = 10;
-        let size = 10000 * 1000;
+        let size = 10000 * cursors;
         let params = &(size, cursors, step, width);
         let d = &format!("cursors_{cursors}/step_{step}");
 
-        group.bench_function(id::new("naive", d), |b| mc_naive::<Buffer>(b, params));
-        group.bench_function(id::new("smart", d), |b| mc_smart::<Buffer>(b, params));
+        mc_compare::<Buffer>(&mut group, buffer, d, params);
+        mc_compare::<Crop>(&mut group, crop, d, params);
+        mc_compare::<JumpRope>(&mut group, jumprope, d, params);
+        mc_compare::<Ropey>(&mut group, ropey, d, params);
     }
 
+    // The points above have a cursor every 10KB, too sparse for `apply_batch` to rebuild the
+    // ropes. Here the document is only as long as the cursors need, so it does.
+    let (cursors, step, width) = (scaled_counts(&[1000])[0], 100, 10);
+    let params = &(cursors * (step + width), cursors, step, width);
+    let d = &format!("cursors_{cursors}/step_{step}/dense");
+    mc_compare::<Buffer>(&mut group, buffer, d, params);
+    mc_compare::<Crop>(&mut group, crop, d, params);
+    mc_compare::<JumpRope>(&mut group, jumprope, d, params);
+    mc_compare::<Ropey>(&mut group, ropey, d, params);
+
     group.finish();
 }
 
@@ -514,11 +1246,12 @@ fn bench_mc_cursors(c: &mut Criterion) {
     let mut group = c.benchmark_group("mc_cursor_count");
     use BenchmarkId as id;
 
-    let max = 10000;
+    let counts = scaled_counts(&[10, 100, 250, 500, 1000, 2000, 5000, 7000, 10000]);
+    let max = *counts.last().unwrap();
     let step = 100;
     let width = 10;
     let size = max * step;
-    for cursors in [10, 100, 250, 500, 1000, 2000, 5000, 7000, max] {
+    for cursors in counts {
         let params = &(size, cursors, step, width);
         group.bench_function(id::new("buffer", cursors), |b| {
             mc_smart::<Buffer>(b, params)
@@ -538,7 +1271,7 @@ fn bench_mc_size(c: &mut Criterion) {
     use BenchmarkId as id;
 
     let max = 6000;
-    let cursors = 100;
+    let cursors = scaled_counts(&[100])[0];
     let width = 10;
     let size = max * cursors;
     for step in [
@@ -557,21 +1290,26 @@ fn bench_mc_size(c: &mut Criterion) {
 }
 
 fn bench_search_linewise(c: &mut Criterion) {
-    let mut group = c.benchmark_group("search_linewise");
+    let corpus = &Corpus::choose("realworld");
+    let mut group = c.benchmark_group(corpus.group("search_linewise"));
     group.sample_size(50);
 
-    group.bench_function("buffer", search_linewise::<Buffer>);
-    group.bench_function("jumprope", search_linewise::<JumpRope>);
-    group.bench_function("ropey", search_linewise::<Ropey>);
-    group.bench_function("crop", search_linewise::<Crop>);
+    let size = scaled(usize::pow(2, 20));
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function("buffer", |b| search_linewise::<Buffer>(b, corpus, size));
+    group.bench_function("jumprope", |b| search_linewise::<JumpRope>(b, corpus, size));
+    group.bench_function("ropey", |b| search_linewise::<Ropey>(b, corpus, size));
+    group.bench_function("crop", |b| search_linewise::<Crop>(b, corpus, size));
     group.finish();
 }
 
 fn bench_search_full(c: &mut Criterion) {
-    let mut group = c.benchmark_group("search_full");
+    let corpus = Corpus::choose("realworld");
+    let name = &corpus.group("search_full");
+    let mut group = c.benchmark_group(name);
     use BenchmarkId as id;
-    let step = usize::pow(2, 27);
-    let small = usize::pow(2, 20);
+    let step = scaled(usize::pow(2, 27));
+    let small = scaled(usize::pow(2, 20));
     for (size, sample) in [
         (small, 100),
         (step, 100),
@@ -583,10 +1321,14 @@ fn bench_search_full(c: &mut Criterion) {
         (step * 7, 50),
         (step * 8, 50),
     ] {
-        let base = gen_realworld_text(size);
+        // The text and a rope built from it.
+        if !memory::fits(name, size, memory::TWO_COPIES * size) {
+            continue;
+        }
+        let base = corpus.text(size);
         let text = base.as_str();
         group.sample_size(sample);
-        group.bench_function(id::new("move_gap", size), |b| move_gap::<Buffer>(b, text));
+        group.throughput(Throughput::Bytes(size as u64));
         group.bench_function(id::new("buffer", size), |b| search_full::<Buffer>(b, text));
         group.bench_function(id::new("crop", size), |b| search_full::<Crop>(b, text));
         group.bench_function(id::new("jumprope", size), |b| {
@@ -598,38 +1340,146 @@ fn bench_search_full(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_move_gap(c: &mut Criterion) {
+    use BenchmarkId as id;
+    let corpus = Corpus::choose("realworld");
+    for size in [usize::pow(2, 20), usize::pow(2, 24), usize::pow(2, 27)].map(scaled) {
+        let name = corpus.group(&format!("move_gap_{size}"));
+        if !memory::fits(&name, size, memory::TWO_COPIES * size) {
+            continue;
+        }
+        let mut group = c.benchmark_group(name);
+        let base = corpus.text(size);
+        let text = base.as_str();
+        let distances = std::iter::successors(Some(1), |d| Some(d * 8))
+            .take_while(|d| *d < size)
+            .chain([size]);
+        for distance in distances {
+            group.bench_function(id::new("buffer", distance), |b| {
+                move_gap::<Buffer>(b, text, distance)
+            });
+            group.bench_function(id::new("crop", distance), |b| {
+                move_gap::<Crop>(b, text, distance)
+            });
+            group.bench_function(id::new("jumprope", distance), |b| {
+                move_gap::<JumpRope>(b, text, distance)
+            });
+            group.bench_function(id::new("ropey", distance), |b| {
+                move_gap::<Ropey>(b, text, distance)
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_random_edit(c: &mut Criterion) {
+    let corpus = Corpus::choose("realworld");
+    let mut group = c.benchmark_group(corpus.group("random_edit"));
+    use BenchmarkId as id;
+
+    for size in [usize::pow(2, 16), usize::pow(2, 20), usize::pow(2, 24)].map(scaled) {
+        // The text and the rope being edited.
+        if !memory::fits(
+            &corpus.group("random_edit"),
+            size,
+            memory::TWO_COPIES * size,
+        ) {
+            continue;
+        }
+        let base = corpus.text(size);
+        let text = base.as_str();
+        for locality in [
+            Locality::Uniform,
+            Locality::Zipf,
+            Locality::RandomWalk(16),
+            Locality::RandomWalk(1024),
+            Locality::RandomWalk(65536),
+        ] {
+            let name = locality.name();
+            group.bench_function(id::new(format!("buffer_{name}"), size), |b| {
+                random_edits::<Buffer>(b, text, locality)
+            });
+            group.bench_function(id::new(format!("crop_{name}"), size), |b| {
+                random_edits::<Crop>(b, text, locality)
+            });
+            group.bench_function(id::new(format!("jumprope_{name}"), size), |b| {
+                random_edits::<JumpRope>(b, text, locality)
+            });
+            group.bench_function(id::new(format!("ropey_{name}"), size), |b| {
+                random_edits::<Ropey>(b, text, locality)
+            });
+        }
+    }
+    group.finish();
+}
+
 fn bench_build_string(c: &mut Criterion) {
-    let mut group = c.benchmark_group("build_string");
+    let corpus = &Corpus::choose("realworld");
+    let name = &corpus.group("build_string");
+    let mut group = c.benchmark_group(name);
 
     for (size, sample) in &[(10, 100), (20, 50), (30, 10)] {
+        let size = &scaled(usize::pow(2, *size));
+        // The rope and the string built from it.
+        if !memory::fits(name, size, memory::TWO_COPIES * size) {
+            continue;
+        }
         group.sample_size(*sample);
-        let size = &usize::pow(2, *size);
+        group.throughput(Throughput::Bytes(*size as u64));
         let id = BenchmarkId::new("buffer", size);
-        group.bench_with_input(id, size, build_string::<Buffer>);
+        group.bench_with_input(id, size, |b, size| build_string::<Buffer>(b, size, corpus));
         let id = BenchmarkId::new("jumprope", size);
-        group.bench_with_input(id, size, build_string::<JumpRope>);
+        group.bench_with_input(id, size, |b, size| {
+            build_string::<JumpRope>(b, size, corpus)
+        });
         let id = BenchmarkId::new("ropey", size);
-        group.bench_with_input(id, size, build_string::<Ropey>);
+        group.bench_with_input(id, size, |b, size| build_string::<Ropey>(b, size, corpus));
         let id = BenchmarkId::new("crop", size);
-        group.bench_with_input(id, size, build_string::<Crop>);
+        group.bench_with_input(id, size, |b, size| build_string::<Crop>(b, size, corpus));
     }
     group.finish();
 }
 
-fn load_named_data(name: &str) -> TestData {
-    let filename = format!(
+fn named_data_path(name: &str) -> String {
+    format!(
         "{}/benchmark_data/{name}.json.gz",
         env!("CARGO_MANIFEST_DIR")
-    );
-    load_testing_data(&filename)
+    )
 }
 
-fn load_named_ascii_data(name: &str) -> TestData {
-    let filename = format!(
+fn named_ascii_data_path(name: &str) -> String {
+    format!(
         "{}/benchmark_data/ascii_only/{name}.json.gz",
         env!("CARGO_MANIFEST_DIR"),
-    );
-    load_testing_data(&filename)
+    )
+}
+
+fn load_named_data(name: &str) -> TestData {
+    scaled_trace(load_valid_data(&named_data_path(name)))
+}
+
+fn load_named_ascii_data(name: &str) -> TestData {
+    scaled_trace(load_valid_data(&named_ascii_data_path(name)))
+}
+
+/// Load a trace, making sure it is consistent so a bad position doesn't show up as a panic
+/// inside one of the ropes.
+fn load_valid_data(filename: &str) -> TestData {
+    let data = load_testing_data(filename);
+    if let Err(e) = data.validate() {
+        panic!("Invalid trace {}: {}", filename, e);
+    }
+    data
+}
+
+/// The trace to benchmark instead of `data`: the same, or only its first 1/256 patches in a
+/// quick run.
+fn scaled_trace(data: TestData) -> TestData {
+    if quick() {
+        data.prefix(scaled(data.len())).unwrap()
+    } else {
+        data
+    }
 }
 
 const DATASETS: &[&str] = &[
@@ -640,31 +1490,74 @@ const DATASETS: &[&str] = &[
     "friendsforever_flat",
 ];
 
+/// Build a rope holding the trace's starting document.
+fn replay_init<R: Rope>(test_data: &TestData) -> R {
+    R::from(test_data.start_content.clone())
+}
+
+fn replay_edits<R: Rope>(r: &mut R, test_data: &TestData) {
+    for txn in &test_data.txns {
+        for TestPatch(pos, del, ins) in &txn.patches {
+            r.edit_at(*pos, *del, ins);
+        }
+    }
+}
+
+/// Benchmark replaying a trace, reporting the initialisation from `start_content` (as
+/// `<rope>_init`, in bytes of start content per second) separately from the edits themselves
+/// (in patches per second). `<rope>_batch` applies each transaction with `apply_batch` instead of
+/// patch by patch. Traces which start from an empty document have nothing to initialise, so they
+/// have no `<rope>_init`.
+fn replay<R: Rope>(group: &mut BenchmarkGroup<WallTime>, name: &str, test_data: &TestData) {
+    if !test_data.start_content.is_empty() {
+        group.throughput(Throughput::Bytes(test_data.start_content.len() as u64));
+        group.bench_function(BenchmarkId::new(format!("{}_init", R::NAME), name), |b| {
+            b.iter(|| black_box(replay_init::<R>(test_data)))
+        });
+    }
+    group.throughput(Throughput::Elements(test_data.len() as u64));
+    group.bench_function(BenchmarkId::new(R::NAME, name), |b| {
+        b.iter_batched(
+            || replay_init::<R>(test_data),
+            |mut r| {
+                replay_edits(&mut r, test_data);
+                assert_eq!(r.char_len(), test_data.end_content.len());
+                black_box(r.char_len());
+                r
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    let mut r = replay_init::<R>(test_data);
+    for txn in &test_data.txns {
+        r.apply_batch(&txn.patches);
+    }
+    assert_eq!(r.get_string(), test_data.end_content, "{}_batch", R::NAME);
+    group.bench_function(BenchmarkId::new(format!("{}_batch", R::NAME), name), |b| {
+        b.iter_batched(
+            || replay_init::<R>(test_data),
+            |mut r| {
+                for txn in &test_data.txns {
+                    r.apply_batch(&txn.patches);
+                }
+                assert_eq!(r.char_len(), test_data.end_content.len());
+                r
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
 fn realworld_unicode(c: &mut Criterion) {
     for name in DATASETS {
         let mut group = c.benchmark_group("realworld_unicode");
         let test_data = load_named_data(name);
 
-        fn x<R: Rope>(group: &mut BenchmarkGroup<WallTime>, name: &str, test_data: &TestData) {
-            group.bench_function(BenchmarkId::new(R::NAME, name), |b| {
-                b.iter(|| {
-                    let mut r = R::new();
-                    for txn in &test_data.txns {
-                        for TestPatch(pos, del, ins) in &txn.patches {
-                            r.edit_at(*pos, *del, ins);
-                        }
-                    }
-                    assert_eq!(r.char_len(), test_data.end_content.len());
-                    black_box(r.char_len());
-                })
-            });
-        }
-
-        // x::<Buffer>(&mut group, name, &test_data);
-        x::<JumpRope>(&mut group, name, &test_data);
-        x::<Ropey>(&mut group, name, &test_data);
+        // replay::<Buffer>(&mut group, name, &test_data);
+        replay::<JumpRope>(&mut group, name, &test_data);
+        replay::<Ropey>(&mut group, name, &test_data);
         // doesn't support unicode indexing
-        // x::<Crop>(&mut group, name, &test_data);
+        // replay::<Crop>(&mut group, name, &test_data);
         group.finish();
     }
 }
@@ -674,44 +1567,878 @@ fn realworld_ascii(c: &mut Criterion) {
         let mut group = c.benchmark_group("realworld_ascii");
         let test_data = load_named_ascii_data(name);
 
-        fn x<R: Rope>(group: &mut BenchmarkGroup<WallTime>, name: &str, test_data: &TestData) {
-            group.bench_function(BenchmarkId::new(R::NAME, name), |b| {
-                b.iter(|| {
-                    let mut r = R::new();
-                    for txn in &test_data.txns {
-                        for TestPatch(pos, del, ins) in &txn.patches {
-                            r.edit_at(*pos, *del, ins);
-                        }
-                    }
-                    assert_eq!(r.char_len(), test_data.end_content.len());
-                    black_box(r.char_len());
-                })
-            });
+        replay::<Buffer>(&mut group, name, &test_data);
+        replay::<Crop>(&mut group, name, &test_data);
+        replay::<JumpRope>(&mut group, name, &test_data);
+        replay::<Ropey>(&mut group, name, &test_data);
+        group.finish();
+    }
+}
+
+/// The ASCII traces with every newline written as "\r\n", or alternating between "\n" and "\r\n".
+fn realworld_crlf(c: &mut Criterion) {
+    for name in DATASETS {
+        let mut group = c.benchmark_group("realworld_crlf");
+        let ascii = load_named_ascii_data(name);
+
+        for ending in [LineEnding::Crlf, LineEnding::Mixed] {
+            let test_data = ascii.with_line_endings(ending);
+            let name = &format!("{}/{}", name, ending.name());
+            replay::<Buffer>(&mut group, name, &test_data);
+            replay::<Crop>(&mut group, name, &test_data);
+            replay::<JumpRope>(&mut group, name, &test_data);
+            replay::<Ropey>(&mut group, name, &test_data);
+        }
+        group.finish();
+    }
+}
+
+/// Text which is hard to move through a grapheme at a time: emoji made of several code points
+/// joined with ZWJs or modifiers, stacks of combining marks, and CJK.
+const GRAPHEME_SAMPLES: &[(&str, &str)] = &[
+    (
+        "emoji",
+        "👨\u{200D}👩\u{200D}👧\u{200D}👦 🏳\u{FE0F}\u{200D}🌈 👍🏽 🇯🇵🇫🇷 ❤\u{FE0F}\u{200D}🔥 1\u{FE0F}\u{20E3} ",
+    ),
+    (
+        "combining",
+        "Z\u{324}\u{354}\u{367}\u{311}\u{343}a\u{308}\u{356}\u{32D} Tie\u{302}\u{301}ng Vie\u{323}\u{302}t ",
+    ),
+    ("cjk", "日本語のテキスト、中文文本。한국어 텍스트 "),
+];
+
+const GRAPHEME_MOVES: usize = 1000;
+
+/// Move a cursor forward `GRAPHEME_MOVES` graphemes from the middle of the text, then back to
+/// where it started.
+fn move_by_graphemes<R: Rope>(
+    group: &mut BenchmarkGroup<WallTime>,
+    id: &str,
+    kind: &str,
+    text: &str,
+) {
+    let r = R::from(text.to_string());
+    let (start_byte, _) = text
+        .grapheme_indices(true)
+        .find(|(i, _)| *i >= text.len() / 2)
+        .unwrap();
+    let start = R::unit_len(&text[..start_byte]);
+
+    // Check every step against unicode-segmentation before timing anything.
+    let mut stops = vec![start];
+    for grapheme in text[start_byte..].graphemes(true).take(GRAPHEME_MOVES) {
+        stops.push(stops.last().unwrap() + R::unit_len(grapheme));
+    }
+    for w in stops.windows(2) {
+        assert_eq!(
+            r.next_grapheme_boundary(w[0]),
+            w[1],
+            "{} moving through {}",
+            id,
+            kind
+        );
+        assert_eq!(
+            r.prev_grapheme_boundary(w[1]),
+            w[0],
+            "{} moving back through {}",
+            id,
+            kind
+        );
+    }
+
+    group.bench_function(BenchmarkId::new(id, kind), |b| {
+        b.iter(|| {
+            let mut pos = start;
+            for _ in 0..GRAPHEME_MOVES {
+                pos = r.next_grapheme_boundary(pos);
+            }
+            for _ in 0..GRAPHEME_MOVES {
+                pos = r.prev_grapheme_boundary(pos);
+            }
+            assert_eq!(pos, start);
+        })
+    });
+}
+
+fn bench_graphemes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("graphemes");
+    let size = usize::pow(2, 20);
+
+    let mut texts: Vec<(&str, String)> = GRAPHEME_SAMPLES
+        .iter()
+        .map(|(kind, sample)| (*kind, sample.repeat(size / sample.len())))
+        .collect();
+    texts.push(("corpus", gen_unicode_text(size)));
+    for (kind, text) in &texts {
+        move_by_graphemes::<Buffer>(&mut group, "buffer", kind, text);
+        move_by_graphemes::<Crop>(&mut group, "crop", kind, text);
+        move_by_graphemes::<JumpRope>(&mut group, "jumprope", kind, text);
+        move_by_graphemes::<Ropey>(&mut group, "ropey", kind, text);
+    }
+    group.finish();
+}
+
+/// A position for the line/column benchmark, with everything worked out from the text itself.
+struct LineColPos {
+    byte: usize,
+    char: usize,
+    line: usize,
+    /// The column in each of `Unit::ALL`.
+    cols: [usize; 3],
+}
+
+/// `count` random positions in `text`, in the order they were generated.
+fn line_col_positions(text: &str, count: usize) -> Vec<LineColPos> {
+    let mut rng = SplitMix64(count as u64);
+    let mut bytes: Vec<(usize, usize)> = (0..count)
+        .map(|i| {
+            let mut byte = (rng.next() % (text.len() as u64 + 1)) as usize;
+            while !text.is_char_boundary(byte) {
+                byte -= 1;
+            }
+            (byte, i)
+        })
+        .collect();
+    bytes.sort_unstable();
+
+    // Count chars and lines in a single pass by visiting the positions in order.
+    let mut positions: Vec<Option<LineColPos>> = (0..count).map(|_| None).collect();
+    let (mut last, mut char, mut line, mut line_start) = (0, 0, 0, 0);
+    for (byte, i) in bytes {
+        let between = &text[last..byte];
+        char += between.chars().count();
+        if let Some(newline) = between.rfind('\n') {
+            line += between.matches('\n').count();
+            line_start = last + newline + 1;
+        }
+        let prefix = &text[line_start..byte];
+        positions[i] = Some(LineColPos {
+            byte,
+            char,
+            line,
+            cols: Unit::ALL.map(|unit| unit.count(prefix)),
+        });
+        last = byte;
+    }
+    positions.into_iter().map(Option::unwrap).collect()
+}
+
+/// Convert random positions to (line, column) and back, one conversion per iteration.
+fn line_col<R: Rope>(
+    group: &mut BenchmarkGroup<WallTime>,
+    id: &str,
+    text: &str,
+    positions: &[LineColPos],
+) {
+    let id = &if R::INDEXES_LINES {
+        id.to_string()
+    } else {
+        format!("{id}_fallback")
+    };
+    let r = R::from(text.to_string());
+    let offset = |p: &LineColPos| {
+        if R::EDITS_USE_BYTE_OFFSETS {
+            p.byte
+        } else {
+            p.char
+        }
+    };
+
+    for (i, unit) in Unit::ALL.iter().copied().enumerate() {
+        // Conversions can take a while on ropes without a line index, so only check a few.
+        for p in positions.iter().take(20) {
+            let line_col = (p.line, p.cols[i]);
+            assert_eq!(r.offset_to_line_col(offset(p), unit), line_col, "{}", id);
+            assert_eq!(
+                r.line_col_to_offset(p.line, p.cols[i], unit),
+                offset(p),
+                "{}",
+                id
+            );
+        }
+
+        let mut next = positions.iter().cycle();
+        let name = format!("{id}_to_line_col");
+        group.bench_function(BenchmarkId::new(name, unit.name()), |b| {
+            b.iter(|| r.offset_to_line_col(offset(next.next().unwrap()), unit))
+        });
+        let mut next = positions.iter().cycle();
+        let name = format!("{id}_to_offset");
+        group.bench_function(BenchmarkId::new(name, unit.name()), |b| {
+            b.iter(|| {
+                let p = next.next().unwrap();
+                r.line_col_to_offset(p.line, p.cols[i], unit)
+            })
+        });
+    }
+}
+
+fn bench_line_col(c: &mut Criterion) {
+    let corpus = Corpus::choose("unicode");
+    let name = corpus.group("line_col");
+    let size = memory::shrink(&name, scaled(100 * usize::pow(2, 20)), |size| {
+        memory::TWO_COPIES * size
+    });
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    let text = &corpus.text(size);
+    let positions = &line_col_positions(text, 1000);
+
+    line_col::<Buffer>(&mut group, "buffer", text, positions);
+    line_col::<Crop>(&mut group, "crop", text, positions);
+    line_col::<JumpRope>(&mut group, "jumprope", text, positions);
+    line_col::<Ropey>(&mut group, "ropey", text, positions);
+    group.finish();
+}
+
+/// The number of lines and their total length in bytes, without line breaks.
+fn line_totals<R>(r: &R, for_each_line: fn(&R, &mut dyn FnMut(usize))) -> (usize, usize) {
+    let (mut lines, mut len) = (0, 0);
+    for_each_line(r, &mut |line| {
+        lines += 1;
+        len += line;
+    });
+    (lines, len)
+}
+
+fn lines_with<R: Rope>(
+    group: &mut BenchmarkGroup<WallTime>,
+    id: &str,
+    ending: LineEnding,
+    text: &str,
+    for_each_line: fn(&R, &mut dyn FnMut(usize)),
+) {
+    let r = R::from(text.to_string());
+    let breaks = text.matches('\n').count();
+    let expected = (breaks + 1, text.len() - breaks - text.matches('\r').count());
+    assert_eq!(
+        line_totals(&r, for_each_line),
+        expected,
+        "{} disagrees about the lines in {} text",
+        id,
+        ending.name()
+    );
+    assert_eq!(
+        r.line_of(r.char_len()),
+        breaks,
+        "{} miscounts line breaks",
+        id
+    );
+
+    group.bench_function(BenchmarkId::new(id, ending.name()), |b| {
+        b.iter(|| line_totals(&r, for_each_line))
+    });
+}
+
+/// Iterate over every line of realworld text with each kind of line ending. Crop is run twice, with
+/// `lines` stripping the line breaks itself and with `raw_lines` leaving them for us.
+fn bench_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lines");
+    let text = gen_realworld_text(usize::pow(2, 20));
+
+    for ending in [LineEnding::Lf, LineEnding::Crlf, LineEnding::Mixed] {
+        let text = &convert_line_endings(&text, ending);
+        lines_with::<Buffer>(&mut group, "buffer", ending, text, |r, f| {
+            r.for_each_line(f)
+        });
+        lines_with::<Crop>(&mut group, "crop", ending, text, |r, f| r.for_each_line(f));
+        lines_with::<Crop>(&mut group, "crop_raw", ending, text, |r, f| {
+            crop_raw_lines(r, f)
+        });
+        lines_with::<JumpRope>(&mut group, "jumprope", ending, text, |r, f| {
+            r.for_each_line(f)
+        });
+        lines_with::<Ropey>(&mut group, "ropey", ending, text, |r, f| r.for_each_line(f));
+    }
+    group.finish();
+}
+
+/// The reads an editor performs after every keystroke, e.g. to redraw the cursor's line.
+#[derive(Clone, Copy)]
+struct Reads {
+    /// Find the line the cursor is on.
+    line: bool,
+    /// Convert the cursor position from chars to bytes and back (or the reverse, for ropes which
+    /// use byte offsets).
+    char_byte: bool,
+    /// Read this many lines either side of the cursor.
+    context_lines: usize,
+}
+
+const READ_CONFIGS: &[(&str, Reads)] = &[
+    (
+        "cursor",
+        Reads {
+            line: true,
+            char_byte: true,
+            context_lines: 0,
+        },
+    ),
+    (
+        "context",
+        Reads {
+            line: false,
+            char_byte: false,
+            context_lines: 5,
+        },
+    ),
+    (
+        "all",
+        Reads {
+            line: true,
+            char_byte: true,
+            context_lines: 5,
+        },
+    ),
+];
+
+fn read_at_cursor<R: Rope>(r: &R, cursor: usize, reads: Reads) {
+    if reads.line {
+        black_box(r.line_of(cursor));
+    }
+    if reads.char_byte {
+        if R::EDITS_USE_BYTE_OFFSETS {
+            black_box(r.char_to_byte(r.byte_to_char(cursor)));
+        } else {
+            black_box(r.byte_to_char(r.char_to_byte(cursor)));
         }
+    }
+    if reads.context_lines > 0 {
+        let line = r.line_of(cursor);
+        let start = r.line_to_offset(line.saturating_sub(reads.context_lines));
+        let end = r.line_to_offset(line + reads.context_lines + 1);
+        r.for_each_chunk(start..end, |chunk| {
+            black_box(chunk);
+            true
+        });
+    }
+}
+
+/// Replay a trace, reading around the cursor after every patch.
+fn read_after_edit<R: Rope>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    test_data: &TestData,
+    config: &str,
+    reads: Reads,
+) {
+    let lines = reads.line || reads.context_lines > 0;
+    let fallback = lines && !R::INDEXES_LINES || reads.char_byte && !R::INDEXES_CHAR_BYTE;
+    let rope = if fallback {
+        format!("{}_fallback", R::NAME)
+    } else {
+        R::NAME.to_string()
+    };
+    group.bench_function(BenchmarkId::new(rope, format!("{name}/{config}")), |b| {
+        b.iter_batched(
+            || replay_init::<R>(test_data),
+            |mut r| {
+                for TestPatch(pos, del, ins) in test_data.patches() {
+                    r.edit_at(*pos, *del, ins);
+                    read_at_cursor(&r, pos + R::unit_len(ins), reads);
+                }
+                assert_eq!(r.char_len(), test_data.end_content.len());
+                r
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_read_after_edit(c: &mut Criterion) {
+    for name in DATASETS {
+        let mut group = c.benchmark_group("read_after_edit");
+        group.sample_size(10);
+        let test_data = load_named_ascii_data(name);
+
+        for (config, reads) in READ_CONFIGS {
+            read_after_edit::<Buffer>(&mut group, name, &test_data, config, *reads);
+            read_after_edit::<Crop>(&mut group, name, &test_data, config, *reads);
+            read_after_edit::<JumpRope>(&mut group, name, &test_data, config, *reads);
+            read_after_edit::<Ropey>(&mut group, name, &test_data, config, *reads);
+        }
+        group.finish();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Word,
+    Number,
+    Space,
+    Punct,
+}
+
+/// A tiny streaming tokenizer standing in for an incremental parser like tree-sitter. It splits
+/// text into words, numbers, whitespace and punctuation, carrying its state across chunks.
+#[derive(Default)]
+struct Tokenizer {
+    current: Option<TokenKind>,
+    tokens: usize,
+}
+
+impl Tokenizer {
+    fn feed(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            let kind = match c {
+                c if c.is_ascii_digit() && self.current != Some(TokenKind::Word) => {
+                    TokenKind::Number
+                }
+                c if c.is_alphanumeric() || c == '_' => TokenKind::Word,
+                c if c.is_whitespace() => TokenKind::Space,
+                _ => TokenKind::Punct,
+            };
+            if self.current != Some(kind) || kind == TokenKind::Punct {
+                self.tokens += 1;
+                self.current = Some(kind);
+            }
+        }
+    }
+}
+
+/// Replay a trace, re-tokenizing a window of `window` bytes centred on each edit. The traces
+/// are ASCII, so offsets are bytes for every rope.
+fn reparse<R: Rope>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    test_data: &TestData,
+    window: usize,
+) {
+    group.bench_function(BenchmarkId::new(R::NAME, format!("{name}/{window}")), |b| {
+        b.iter_batched(
+            || replay_init::<R>(test_data),
+            |mut r| {
+                for TestPatch(pos, del, ins) in test_data.patches() {
+                    r.edit_at(*pos, *del, ins);
+                    let start = pos.saturating_sub(window / 2);
+                    let end = (pos + ins.len() + window / 2).min(r.char_len());
+                    let mut tokenizer = Tokenizer::default();
+                    r.for_each_chunk(start..end, |chunk| {
+                        tokenizer.feed(chunk);
+                        true
+                    });
+                    black_box(tokenizer.tokens);
+                }
+                assert_eq!(r.char_len(), test_data.end_content.len());
+                r
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_reparse(c: &mut Criterion) {
+    for name in DATASETS {
+        let mut group = c.benchmark_group("reparse");
+        group.sample_size(10);
+        let test_data = load_named_ascii_data(name);
+
+        for window in [256, 4096] {
+            reparse::<Buffer>(&mut group, name, &test_data, window);
+            reparse::<Crop>(&mut group, name, &test_data, window);
+            reparse::<JumpRope>(&mut group, name, &test_data, window);
+            reparse::<Ropey>(&mut group, name, &test_data, window);
+        }
+        group.finish();
+    }
+}
+
+/// Greedy word wrap, counting each char as one column. It's fed a line chunk by chunk and counts
+/// the visual rows it wraps to.
+struct Wrapper {
+    width: usize,
+    col: usize,
+    /// Chars since the last space, which move down to the next row with their word.
+    since_space: usize,
+    rows: usize,
+}
+
+impl Wrapper {
+    fn new(width: usize) -> Self {
+        Wrapper {
+            width,
+            col: 0,
+            since_space: 0,
+            rows: 1,
+        }
+    }
+
+    fn feed(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            if c == '\n' || c == '\r' {
+                continue;
+            }
+            if c == ' ' {
+                // Spaces at the end of a row hang off the edge rather than wrapping.
+                self.col = (self.col + 1).min(self.width);
+                self.since_space = 0;
+                continue;
+            }
+            if self.col == self.width {
+                self.rows += 1;
+                // Words which are too long for a row are split wherever the row ends.
+                self.col = if self.since_space < self.col {
+                    self.since_space
+                } else {
+                    0
+                };
+            }
+            self.col += 1;
+            self.since_space += 1;
+        }
+    }
+}
+
+const WRAP_WIDTH: usize = 80;
+const VIEWPORT_ROWS: usize = 50;
+
+/// The number of visual rows each line wraps to, or None if it has to be laid out again.
+struct WrapCache {
+    rows: Vec<Option<usize>>,
+}
+
+impl WrapCache {
+    fn new<R: Rope>(r: &R) -> Self {
+        WrapCache {
+            rows: vec![None; r.line_of(r.char_len()) + 1],
+        }
+    }
+
+    /// Throw away the layout of every line an edit touches. `removed` and `added` are the number
+    /// of line breaks it deleted and inserted.
+    fn invalidate(&mut self, first: usize, removed: usize, added: usize) {
+        let lines = first..first + removed + 1;
+        self.rows.splice(lines, (0..=added).map(|_| None));
+    }
 
-        x::<Buffer>(&mut group, name, &test_data);
-        x::<Crop>(&mut group, name, &test_data);
-        x::<JumpRope>(&mut group, name, &test_data);
-        x::<Ropey>(&mut group, name, &test_data);
+    fn rows<R: Rope>(&mut self, r: &R, line: usize, cached: bool) -> usize {
+        if let Some(rows) = self.rows[line].filter(|_| cached) {
+            return rows;
+        }
+        let mut wrapper = Wrapper::new(WRAP_WIDTH);
+        let range = r.line_to_offset(line)..r.line_to_offset(line + 1);
+        r.for_each_chunk(range, |chunk| {
+            wrapper.feed(chunk);
+            true
+        });
+        self.rows[line] = Some(wrapper.rows);
+        wrapper.rows
+    }
+
+    /// Lay out `VIEWPORT_ROWS` visual rows around the cursor's line, returning the range of lines
+    /// on screen.
+    fn viewport<R: Rope>(&mut self, r: &R, cursor_line: usize, cached: bool) -> Range<usize> {
+        let mut start = cursor_line;
+        let mut above = 0;
+        while start > 0 && above < VIEWPORT_ROWS / 2 {
+            start -= 1;
+            above += self.rows(r, start, cached);
+        }
+        let mut end = cursor_line;
+        let mut below = 0;
+        while end < self.rows.len() && above + below < VIEWPORT_ROWS {
+            below += self.rows(r, end, cached);
+            end += 1;
+        }
+        start..end
+    }
+}
+
+/// Replay a trace, keeping the viewport around the cursor soft wrapped after every patch. With
+/// `cached`, each line's layout is kept until an edit touches it; otherwise every line on screen
+/// is laid out again.
+fn soft_wrap<R: Rope>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    test_data: &TestData,
+    cached: bool,
+) {
+    let rope = if R::INDEXES_LINES {
+        R::NAME.to_string()
+    } else {
+        format!("{}_fallback", R::NAME)
+    };
+    let mode = if cached { "cached" } else { "uncached" };
+    group.bench_function(BenchmarkId::new(rope, format!("{name}/{mode}")), |b| {
+        b.iter_batched(
+            || {
+                let r = replay_init::<R>(test_data);
+                let cache = WrapCache::new(&r);
+                (r, cache)
+            },
+            |(mut r, mut cache)| {
+                for TestPatch(pos, del, ins) in test_data.patches() {
+                    let first = r.line_of(*pos);
+                    let removed = if *del > 0 {
+                        r.line_of(pos + del) - first
+                    } else {
+                        0
+                    };
+                    r.edit_at(*pos, *del, ins);
+                    cache.invalidate(first, removed, ins.matches('\n').count());
+
+                    let cursor_line = r.line_of(pos + R::unit_len(ins));
+                    black_box(cache.viewport(&r, cursor_line, cached));
+                }
+                assert_eq!(r.char_len(), test_data.end_content.len());
+                assert_eq!(cache.rows.len(), r.line_of(r.char_len()) + 1);
+                (r, cache)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_soft_wrap(c: &mut Criterion) {
+    for name in DATASETS {
+        let mut group = c.benchmark_group("soft_wrap");
+        group.sample_size(10);
+        let test_data = load_named_ascii_data(name);
+
+        for cached in [true, false] {
+            soft_wrap::<Buffer>(&mut group, name, &test_data, cached);
+            soft_wrap::<Crop>(&mut group, name, &test_data, cached);
+            soft_wrap::<JumpRope>(&mut group, name, &test_data, cached);
+            soft_wrap::<Ropey>(&mut group, name, &test_data, cached);
+        }
         group.finish();
     }
 }
 
+/// How many times each trace is replayed when measuring transaction latency. The first run is a
+/// warm up and isn't recorded.
+const LATENCY_RUNS: usize = 5;
+
+/// Replay a trace, timing each transaction on its own.
+fn txn_latencies<R: Rope>(test_data: &TestData) -> Vec<Duration> {
+    let mut r = replay_init::<R>(test_data);
+    let mut times = Vec::with_capacity(test_data.txns.len());
+    for txn in &test_data.txns {
+        let start = Instant::now();
+        for TestPatch(pos, del, ins) in &txn.patches {
+            r.edit_at(*pos, *del, ins);
+        }
+        times.push(start.elapsed());
+    }
+    assert_eq!(r.char_len(), test_data.end_content.len());
+    black_box(r);
+    times
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let idx = (sorted.len() as f64 * p).ceil() as usize;
+    sorted[idx.saturating_sub(1).min(sorted.len() - 1)]
+}
+
+fn report_latency<R: Rope>(group: &str, name: &str, test_data: &TestData) {
+    if test_data.txns.is_empty() {
+        println!(
+            "{:<40} {:<10} no transactions",
+            format!("{group}/{name}"),
+            R::NAME
+        );
+        return;
+    }
+    txn_latencies::<R>(test_data);
+    let mut times: Vec<Duration> = (1..LATENCY_RUNS)
+        .flat_map(|_| txn_latencies::<R>(test_data))
+        .collect();
+    times.sort_unstable();
+
+    let us = |d: Duration| d.as_secs_f64() * 1e6;
+    println!(
+        "{:<40} {:<10} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+        format!("{group}/{name}"),
+        R::NAME,
+        us(percentile(&times, 0.5)),
+        us(percentile(&times, 0.99)),
+        us(percentile(&times, 0.999)),
+        us(*times.last().unwrap()),
+    );
+}
+
+/// Print the distribution of per-transaction latency (in µs) for every trace. Criterion only
+/// reports the time for a whole trace, which hides the occasional slow edit.
+fn report_txn_latency() {
+    println!(
+        "{:<40} {:<10} {:>10} {:>10} {:>10} {:>10}",
+        "trace", "rope", "p50", "p99", "p99.9", "max"
+    );
+    for name in DATASETS {
+        let test_data = load_named_data(name);
+        report_latency::<JumpRope>("realworld_unicode", name, &test_data);
+        report_latency::<Ropey>("realworld_unicode", name, &test_data);
+
+        let test_data = load_named_ascii_data(name);
+        report_latency::<Buffer>("realworld_ascii", name, &test_data);
+        report_latency::<Crop>("realworld_ascii", name, &test_data);
+        report_latency::<JumpRope>("realworld_ascii", name, &test_data);
+        report_latency::<Ropey>("realworld_ascii", name, &test_data);
+    }
+}
+
 criterion_group!(
     benches,
     bench_create,
     bench_save,
+    bench_file_pipeline,
     bench_append,
     bench_mc_smart,
     bench_mc_cursors,
     bench_mc_size,
     bench_search_linewise,
     bench_search_full,
+    bench_move_gap,
+    bench_random_edit,
     bench_build_string,
     realworld_unicode,
     realworld_ascii,
+    realworld_crlf,
+    bench_lines,
+    bench_graphemes,
+    bench_line_col,
+    bench_read_after_edit,
+    bench_reparse,
+    bench_soft_wrap,
 );
-criterion_main!(benches);
+/// Replay `data` on `R`, returning true if the rope panics or doesn't end up with `end_content`.
+fn rope_fails<R: Rope>(data: &TestData) -> bool {
+    // The rope is expected to panic over and over while bisecting, so keep it quiet.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(|| {
+        let mut r = replay_init::<R>(data);
+        replay_edits(&mut r, data);
+        r.get_string() == data.end_content
+    });
+    std::panic::set_hook(hook);
+    !matches!(result, Ok(true))
+}
+
+/// Replay every trace the realworld groups use, in full, on each rope they use it with, failing
+/// if any rope panics or ends up with the wrong text.
+fn check_traces() {
+    type Check = (&'static str, fn(&TestData) -> bool);
+    let ropes: &[Check] = &[
+        ("buffer", rope_fails::<Buffer>),
+        ("crop", rope_fails::<Crop>),
+        ("jumprope", rope_fails::<JumpRope>),
+        ("ropey", rope_fails::<Ropey>),
+    ];
+    let mut failures = vec![];
+    let mut check = |trace: &str, data: &TestData, ropes: &[Check]| {
+        for (rope, fails) in ropes {
+            if fails(data) {
+                failures.push(format!("{} on {}", rope, trace));
+            }
+        }
+    };
+
+    for name in DATASETS {
+        // Like realworld_unicode, only the ropes indexing by chars.
+        check(name, &load_valid_data(&named_data_path(name)), &ropes[2..]);
+        let ascii = load_valid_data(&named_ascii_data_path(name));
+        check(&format!("{}/ascii", name), &ascii, ropes);
+        for ending in [LineEnding::Crlf, LineEnding::Mixed] {
+            let trace = format!("{}/{}", name, ending.name());
+            check(&trace, &ascii.with_line_endings(ending), ropes);
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "Traces replayed incorrectly (see `minimize`): {}",
+            failures.join(", ")
+        );
+    }
+    println!("Every rope replays every trace correctly");
+}
+
+/// Run every benchmark once, on inputs 256 times smaller, to check the harness and the ropes
+/// work without waiting for a full run. This reruns the benchmarks with `BENCH_QUICK` set and
+/// without `--bench`, so criterion runs each one once instead of measuring it. Any other
+/// arguments, such as a filter, are passed on to criterion.
+///
+/// Usage: --quick [criterion args]
+fn run_quick(args: &[String]) {
+    let status = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(args)
+        .env("BENCH_QUICK", "1")
+        .status()
+        .unwrap();
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn minimize_with<R: Rope>(data: &TestData) -> TestData {
+    let data = if R::EDITS_USE_BYTE_OFFSETS && !data.using_byte_positions {
+        data.chars_to_bytes()
+    } else {
+        data.clone()
+    };
+    if !rope_fails::<R>(&data) {
+        eprintln!(
+            "{} replays the trace correctly, nothing to minimise",
+            R::NAME
+        );
+        std::process::exit(1);
+    }
+    minimize(&data, rope_fails::<R>)
+}
+
+/// Shrink a trace which a rope gets wrong down to a small repro.
+///
+/// Usage: minimize <rope> <trace.json.gz> <out.json.gz>
+fn minimize_trace(args: &[String]) {
+    let [rope, input, output] = args else {
+        panic!("Usage: minimize <rope> <trace.json.gz> <out.json.gz>");
+    };
+    let data = load_valid_data(input);
+
+    let min = match rope.as_str() {
+        "buffer" => minimize_with::<Buffer>(&data),
+        "crop" => minimize_with::<Crop>(&data),
+        "jumprope" => minimize_with::<JumpRope>(&data),
+        "ropey" => minimize_with::<Ropey>(&data),
+        _ => panic!("Unknown rope {}", rope),
+    };
+
+    println!(
+        "Minimised {} patches to {} ({} bytes of start content)",
+        data.len(),
+        min.len(),
+        min.start_content.len()
+    );
+    save_testing_data(output, &min);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("minimize") => minimize_trace(&args[2..]),
+        Some("latency") => report_txn_latency(),
+        Some("file-memory") => report_file_memory(&args[2..]),
+        Some("export") => results::export(&args[2..]),
+        Some("compare") => results::compare(&args[2..]),
+        Some("report") => charts::report(&args[2..]),
+        Some("--quick") => run_quick(&args[2..]),
+        _ => {
+            // A quick run with a filter is after a few benchmarks, not all the traces.
+            let filtered = args[1..].iter().any(|arg| !arg.starts_with('-'));
+            if quick() {
+                if !filtered {
+                    check_traces();
+                }
+            } else if measuring(&args) {
+                results::mark_run();
+            }
+            benches();
+            Criterion::default().configure_from_args().final_summary();
+            if !quick() && measuring(&args) {
+                memory::save_skipped();
+            }
+        }
+    }
+}
 
 // fn main() {
 //     report_space_overhead();
@@ -0,0 +1,141 @@
+use crate::results::criterion_dir;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// A parameter point which would have needed more memory than the budget.
+#[derive(Serialize, Deserialize)]
+pub struct Skipped {
+    pub group: String,
+    pub parameter: String,
+    /// Roughly how many bytes the point needs, and how many it was allowed.
+    pub needed: usize,
+    pub budget: usize,
+    /// The size run instead, if the point was shrunk rather than skipped.
+    pub shrunk_to: Option<usize>,
+}
+
+impl Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mb = |bytes: usize| bytes / usize::pow(2, 20);
+        match self.shrunk_to {
+            Some(size) => write!(f, "{}: shrunk {} to {}", self.group, self.parameter, size)?,
+            None => write!(f, "{}/{}: skipped", self.group, self.parameter)?,
+        }
+        write!(
+            f,
+            " (needs ~{} MB, budget {} MB)",
+            mb(self.needed),
+            mb(self.budget)
+        )
+    }
+}
+
+static SKIPPED: Mutex<Vec<Skipped>> = Mutex::new(vec![]);
+
+// Rough estimates of the memory a point needs, as multiples of the size of its text: one for each
+// copy of the text alive at once, whether a string or a rope, and one more for the ropes'
+// overhead and the allocator's slack.
+pub const TWO_COPIES: usize = 3;
+pub const THREE_COPIES: usize = 4;
+pub const SIX_COPIES: usize = 7;
+
+/// Read `MemAvailable` from `/proc/meminfo`, in bytes.
+fn available() -> Option<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kb: usize = line["MemAvailable:".len()..]
+        .trim()
+        .trim_end_matches(" kB")
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// How many bytes a parameter point may use: `BENCH_MEMORY_BUDGET` (in MB) if it's set, otherwise
+/// 80% of the memory available when the run started. There is no limit if neither is known.
+fn budget() -> Option<usize> {
+    static BUDGET: OnceLock<Option<usize>> = OnceLock::new();
+    *BUDGET.get_or_init(|| match std::env::var("BENCH_MEMORY_BUDGET") {
+        Ok(mb) => {
+            let mb: usize = mb
+                .parse()
+                .expect("BENCH_MEMORY_BUDGET must be a number of MB");
+            Some(mb * usize::pow(2, 20))
+        }
+        Err(_) => available().map(|bytes| bytes / 5 * 4),
+    })
+}
+
+fn record(skipped: Skipped) {
+    eprintln!("Memory: {}", skipped);
+    SKIPPED.lock().unwrap().push(skipped);
+}
+
+/// Whether a point needing about `needed` bytes fits in the budget. If it doesn't, it is recorded
+/// as skipped.
+pub fn fits(group: &str, parameter: impl Display, needed: usize) -> bool {
+    match budget() {
+        Some(budget) if needed > budget => {
+            record(Skipped {
+                group: group.to_string(),
+                parameter: parameter.to_string(),
+                needed,
+                budget,
+                shrunk_to: None,
+            });
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Halve `size` until `needed(size)` fits in the budget, for groups which only run one size.
+/// Shrinking is recorded like skipping.
+pub fn shrink(group: &str, size: usize, needed: impl Fn(usize) -> usize) -> usize {
+    let Some(budget) = budget() else {
+        return size;
+    };
+    let mut shrunk = size;
+    while needed(shrunk) > budget && shrunk > 1 {
+        shrunk /= 2;
+    }
+    if shrunk != size {
+        record(Skipped {
+            group: group.to_string(),
+            parameter: size.to_string(),
+            needed: needed(size),
+            budget,
+            shrunk_to: Some(shrunk),
+        });
+    }
+    shrunk
+}
+
+const SKIPPED_FILE: &str = "skipped.json";
+
+/// Write the points skipped in this run next to criterion's results, so `export` can list them,
+/// and print a summary.
+pub fn save_skipped() {
+    let skipped = SKIPPED.lock().unwrap();
+    let dir = criterion_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let json = serde_json::to_string_pretty(&*skipped).unwrap();
+    fs::write(dir.join(SKIPPED_FILE), json + "\n").unwrap();
+    if !skipped.is_empty() {
+        println!("{} points didn't fit in memory:", skipped.len());
+        for s in skipped.iter() {
+            println!("  {}", s);
+        }
+    }
+}
+
+/// The points skipped in the last run, if it recorded any.
+pub fn load_skipped(dir: &Path) -> Vec<Skipped> {
+    match fs::read_to_string(dir.join(SKIPPED_FILE)) {
+        Ok(json) => serde_json::from_str(&json).unwrap(),
+        Err(_) => vec![],
+    }
+}
@@ -0,0 +1,560 @@
+use crate::memory;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How much work one iteration of a benchmark does.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Throughput {
+    Bytes(u64),
+    Elements(u64),
+}
+
+/// One benchmark's result, as exported by `export`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Record {
+    pub group: String,
+    pub function: String,
+    /// The rope the function name starts with, or the whole name if it isn't one of them.
+    pub rope: String,
+    pub parameter: Option<String>,
+    /// Mean time per iteration in nanoseconds, with its 95% confidence interval.
+    pub mean_ns: f64,
+    pub ci_lower_ns: f64,
+    pub ci_upper_ns: f64,
+    pub throughput: Option<Throughput>,
+    /// When criterion wrote the result, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub measured: u64,
+}
+
+impl Record {
+    /// `group/function/parameter`, as criterion names it.
+    pub fn id(&self) -> String {
+        match &self.parameter {
+            Some(parameter) => format!("{}/{}/{}", self.group, self.function, parameter),
+            None => format!("{}/{}", self.group, self.function),
+        }
+    }
+
+    /// Bytes or elements per second at the mean time.
+    pub fn rate(&self) -> Option<f64> {
+        let amount = match self.throughput? {
+            Throughput::Bytes(n) | Throughput::Elements(n) => n,
+        };
+        Some(amount as f64 / (self.mean_ns / 1e9))
+    }
+}
+
+// The parts of criterion's `benchmark.json` and `estimates.json` we need.
+#[derive(Deserialize)]
+struct CriterionBenchmark {
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    throughput: Option<CriterionThroughput>,
+}
+
+#[derive(Deserialize)]
+enum CriterionThroughput {
+    Bytes(u64),
+    BytesDecimal(u64),
+    Elements(u64),
+}
+
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    confidence_interval: ConfidenceInterval,
+    point_estimate: f64,
+}
+
+#[derive(Deserialize)]
+struct ConfidenceInterval {
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+const ROPES: &[&str] = &["buffer", "crop", "jumprope", "ropey"];
+
+fn rope_of(function: &str) -> String {
+    let function = function.to_lowercase();
+    match ROPES.iter().find(|rope| function.starts_with(*rope)) {
+        Some(rope) => rope.to_string(),
+        None => function,
+    }
+}
+
+/// Touched at the start of every benchmark run, so `export` can tell which results it measured.
+const RUN_MARKER: &str = "run_started";
+
+/// Where criterion writes its results: `$CRITERION_HOME`, `$CARGO_TARGET_DIR/criterion` or
+/// `target/criterion`.
+pub fn criterion_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("CRITERION_HOME") {
+        return home.into();
+    }
+    let target = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target"));
+    target.join("criterion")
+}
+
+/// Note that a run is starting.
+pub fn mark_run() {
+    let dir = criterion_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(RUN_MARKER), "").unwrap();
+}
+
+fn modified(path: &Path) -> SystemTime {
+    fs::metadata(path).unwrap().modified().unwrap()
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> T {
+    let contents = fs::read_to_string(path).unwrap();
+    serde_json::from_str(&contents)
+        .unwrap_or_else(|e| panic!("Couldn't parse {}: {}", path.display(), e))
+}
+
+/// Find every benchmark's latest results under `dir`.
+fn collect(dir: &Path, records: &mut Vec<Record>) {
+    let latest = dir.join("new");
+    if latest.join("benchmark.json").exists() && latest.join("estimates.json").exists() {
+        let benchmark: CriterionBenchmark = read_json(&latest.join("benchmark.json"));
+        let estimates: Estimates = read_json(&latest.join("estimates.json"));
+        let measured = modified(&latest.join("estimates.json"));
+        let function = benchmark.function_id.unwrap_or_default();
+        let mean = estimates.mean;
+        records.push(Record {
+            group: benchmark.group_id,
+            rope: rope_of(&function),
+            function,
+            parameter: benchmark.value_str,
+            mean_ns: mean.point_estimate,
+            ci_lower_ns: mean.confidence_interval.lower_bound,
+            ci_upper_ns: mean.confidence_interval.upper_bound,
+            throughput: benchmark.throughput.map(|t| match t {
+                CriterionThroughput::Bytes(n) | CriterionThroughput::BytesDecimal(n) => {
+                    Throughput::Bytes(n)
+                }
+                CriterionThroughput::Elements(n) => Throughput::Elements(n),
+            }),
+            measured: measured.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        });
+        return;
+    }
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() && path.file_name().is_some_and(|n| n != "report") {
+            collect(&path, records);
+        }
+    }
+}
+
+/// Sort by group, function and then parameter, comparing numeric parameters as numbers.
+fn sort(records: &mut [Record]) {
+    records.sort_by(|a, b| {
+        let key = |r: &Record| {
+            let parameter = r.parameter.clone().unwrap_or_default();
+            (
+                r.group.clone(),
+                r.function.clone(),
+                parameter.parse::<u64>().ok(),
+                parameter,
+            )
+        };
+        key(a).partial_cmp(&key(b)).unwrap()
+    });
+}
+
+/// Read the results written by `export`.
+pub fn load(path: &Path) -> Vec<Record> {
+    read_json(path)
+}
+
+/// Format a time in nanoseconds with a sensible unit.
+pub fn format_time(ns: f64) -> String {
+    match ns {
+        ns if ns < 1e3 => format!("{:.1} ns", ns),
+        ns if ns < 1e6 => format!("{:.2} µs", ns / 1e3),
+        ns if ns < 1e9 => format!("{:.2} ms", ns / 1e6),
+        ns => format!("{:.3} s", ns / 1e9),
+    }
+}
+
+fn format_rate(record: &Record) -> String {
+    match (record.throughput, record.rate()) {
+        (Some(Throughput::Bytes(_)), Some(rate)) => {
+            format!("{:.1} MiB/s", rate / usize::pow(2, 20) as f64)
+        }
+        (Some(Throughput::Elements(_)), Some(rate)) => format!("{:.0} elem/s", rate),
+        _ => String::new(),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_csv(records: &[Record]) -> String {
+    let mut csv = String::from(
+        "group,function,rope,parameter,mean_ns,ci_lower_ns,ci_upper_ns,throughput_kind,throughput,per_second,measured\n",
+    );
+    for r in records {
+        let (kind, amount) = match r.throughput {
+            Some(Throughput::Bytes(n)) => ("bytes", n.to_string()),
+            Some(Throughput::Elements(n)) => ("elements", n.to_string()),
+            None => ("", String::new()),
+        };
+        let rate = r
+            .rate()
+            .map_or(String::new(), |rate| format!("{:.0}", rate));
+        let fields = [
+            csv_field(&r.group),
+            csv_field(&r.function),
+            csv_field(&r.rope),
+            csv_field(r.parameter.as_deref().unwrap_or("")),
+            format!("{:.1}", r.mean_ns),
+            format!("{:.1}", r.ci_lower_ns),
+            format!("{:.1}", r.ci_upper_ns),
+            kind.to_string(),
+            amount,
+            rate,
+            r.measured.to_string(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn to_markdown(records: &[Record], skipped: &[memory::Skipped]) -> String {
+    let mut md =
+        String::from("| group | rope | function | parameter | mean | 95% CI | throughput |\n");
+    md.push_str("|---|---|---|---|---:|---|---:|\n");
+    for r in records {
+        writeln!(
+            md,
+            "| {} | {} | {} | {} | {} | {} – {} | {} |",
+            r.group,
+            r.rope,
+            r.function,
+            r.parameter.as_deref().unwrap_or(""),
+            format_time(r.mean_ns),
+            format_time(r.ci_lower_ns),
+            format_time(r.ci_upper_ns),
+            format_rate(r),
+        )
+        .unwrap();
+    }
+    if !skipped.is_empty() {
+        md.push_str("\nThese points didn't fit in the memory budget:\n\n");
+        for s in skipped {
+            writeln!(md, "- {}", s).unwrap();
+        }
+    }
+    md
+}
+
+/// Gather the results of the last run from criterion's output into `results.json`,
+/// `results.csv` and `results.md` in `out`. Criterion keeps the results of benchmarks which
+/// weren't run, e.g. because a filter or the memory budget left them out, so those are only
+/// exported with `--all`.
+///
+/// Usage: export [--all] [out dir, default results]
+pub fn export(args: &[String]) {
+    let (all, args) = match args {
+        [first, rest @ ..] if first == "--all" => (true, rest),
+        _ => (false, args),
+    };
+    let out = match args {
+        [] => Path::new(env!("CARGO_MANIFEST_DIR")).join("results"),
+        [out] => PathBuf::from(out),
+        _ => panic!("Usage: export [--all] [out dir]"),
+    };
+    let dir = criterion_dir();
+    if !dir.exists() {
+        panic!("No results in {}, run the benchmarks first", dir.display());
+    }
+    let mut records = vec![];
+    collect(&dir, &mut records);
+    sort(&mut records);
+
+    let marker = dir.join(RUN_MARKER);
+    if marker.exists() {
+        let started = modified(&marker)
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let (current, stale): (Vec<Record>, Vec<Record>) =
+            records.into_iter().partition(|r| r.measured >= started);
+        if !stale.is_empty() {
+            eprintln!("{} results are from before the last run:", stale.len());
+            for r in &stale {
+                eprintln!("  {}", r.id());
+            }
+            if !all {
+                eprintln!("Leaving them out; pass --all to export them too");
+            }
+        }
+        records = current;
+        if all {
+            records.extend(stale);
+            sort(&mut records);
+        }
+    }
+    let skipped = memory::load_skipped(&dir);
+
+    fs::create_dir_all(&out).unwrap();
+    let json = serde_json::to_string_pretty(&records).unwrap();
+    fs::write(out.join("results.json"), json + "\n").unwrap();
+    fs::write(out.join("results.csv"), to_csv(&records)).unwrap();
+    fs::write(out.join("results.md"), to_markdown(&records, &skipped)).unwrap();
+    let json = serde_json::to_string_pretty(&skipped).unwrap();
+    fs::write(out.join("skipped.json"), json + "\n").unwrap();
+    println!("Exported {} results to {}", records.len(), out.display());
+}
+
+/// Compare the results in two files written by `export`, printing how much slower or faster each
+/// benchmark got. A change only counts if the confidence intervals don't overlap, and the process
+/// exits with status 1 if any benchmark got slower by more than `threshold` percent.
+///
+/// Usage: compare <before.json> <after.json> [threshold %, default 5]
+pub fn compare(args: &[String]) {
+    let (before, after, threshold) = match args {
+        [before, after] => (before, after, 5.0),
+        [before, after, threshold] => (before, after, threshold.parse().unwrap()),
+        _ => panic!("Usage: compare <before.json> <after.json> [threshold %]"),
+    };
+    let before = load(Path::new(before));
+    let after = load(Path::new(after));
+    let (report, regressions) = comparison(&before, &after, threshold);
+    print!("{}", report);
+    if regressions > 0 {
+        println!(
+            "{} benchmarks regressed by more than {}%",
+            regressions, threshold
+        );
+        std::process::exit(1);
+    }
+}
+
+/// The table `compare` prints, and how many benchmarks regressed by more than `threshold`
+/// percent.
+fn comparison(before: &[Record], after: &[Record], threshold: f64) -> (String, usize) {
+    let before: HashMap<String, &Record> = before.iter().map(|r| (r.id(), r)).collect();
+
+    let mut report = String::new();
+    let mut regressions = 0;
+    let mut missing: Vec<&String> = before.keys().collect();
+    for new in after {
+        let id = new.id();
+        let Some(old) = before.get(&id) else {
+            writeln!(
+                report,
+                "{:<60} {:>12} {:>12}  new",
+                id,
+                "",
+                format_time(new.mean_ns)
+            )
+            .unwrap();
+            continue;
+        };
+        missing.retain(|m| **m != id);
+        let ratio = new.mean_ns / old.mean_ns;
+        let flag = if new.ci_lower_ns > old.ci_upper_ns {
+            if (ratio - 1.0) * 100.0 > threshold {
+                regressions += 1;
+                "REGRESSED"
+            } else {
+                "slower"
+            }
+        } else if new.ci_upper_ns < old.ci_lower_ns {
+            "faster"
+        } else {
+            ""
+        };
+        writeln!(
+            report,
+            "{:<60} {:>12} {:>12} {:>6.2}x  {}",
+            id,
+            format_time(old.mean_ns),
+            format_time(new.mean_ns),
+            ratio,
+            flag
+        )
+        .unwrap();
+    }
+    missing.sort();
+    for id in missing {
+        writeln!(
+            report,
+            "{:<60} {:>12} {:>12}  removed",
+            id,
+            format_time(before[id].mean_ns),
+            ""
+        )
+        .unwrap();
+    }
+    (report, regressions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(group: &str, function: &str, parameter: Option<&str>) -> Record {
+        Record {
+            group: group.into(),
+            function: function.into(),
+            rope: rope_of(function),
+            parameter: parameter.map(Into::into),
+            mean_ns: 2048.0,
+            ci_lower_ns: 2000.0,
+            ci_upper_ns: 2100.0,
+            throughput: Some(Throughput::Bytes(1024)),
+            measured: 7,
+        }
+    }
+
+    #[test]
+    fn ropes() {
+        assert_eq!(rope_of("crop"), "crop");
+        assert_eq!(rope_of("JumpRope"), "jumprope");
+        assert_eq!(rope_of("ropey/smart"), "ropey");
+        assert_eq!(rope_of("Buffer_fallback"), "buffer");
+        assert_eq!(rope_of("String"), "string");
+    }
+
+    #[test]
+    fn csv() {
+        let mut quoted = record("a,\"b\"", "naive", None);
+        quoted.throughput = None;
+        let csv = to_csv(&[record("insert", "crop", Some("1024")), quoted]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "group,function,rope,parameter,mean_ns,ci_lower_ns,ci_upper_ns,throughput_kind,throughput,per_second,measured",
+                "insert,crop,crop,1024,2048.0,2000.0,2100.0,bytes,1024,500000000,7",
+                "\"a,\"\"b\"\"\",naive,naive,,2048.0,2000.0,2100.0,,,,7",
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let mut elements = record("insert", "Ropey", None);
+        elements.throughput = Some(Throughput::Elements(2));
+        let skipped = memory::Skipped {
+            group: "file_open".into(),
+            parameter: "crop/1073741824".into(),
+            needed: 4 << 30,
+            budget: 1 << 30,
+            shrunk_to: None,
+        };
+        let md = to_markdown(
+            &[record("insert", "crop", Some("1024")), elements],
+            &[skipped],
+        );
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "| group | rope | function | parameter | mean | 95% CI | throughput |",
+                "|---|---|---|---|---:|---|---:|",
+                "| insert | crop | crop | 1024 | 2.05 µs | 2.00 µs – 2.10 µs | 476.8 MiB/s |",
+                "| insert | ropey | Ropey |  | 2.05 µs | 2.00 µs – 2.10 µs | 976562 elem/s |",
+                "",
+                "These points didn't fit in the memory budget:",
+                "",
+                "- file_open/crop/1073741824: skipped (needs ~4096 MB, budget 1024 MB)",
+            ]
+        );
+    }
+
+    /// `record` with the given mean and a confidence interval of ±`spread`.
+    fn timed(function: &str, mean_ns: f64, spread: f64) -> Record {
+        Record {
+            mean_ns,
+            ci_lower_ns: mean_ns - spread,
+            ci_upper_ns: mean_ns + spread,
+            ..record("insert", function, Some("1024"))
+        }
+    }
+
+    /// Each line of `compare`'s table, split into its columns.
+    fn columns(report: &str) -> Vec<Vec<&str>> {
+        report
+            .lines()
+            .map(|line| {
+                line.split("  ")
+                    .filter(|c| !c.is_empty())
+                    .map(str::trim)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compare_flags() {
+        let before = [
+            timed("buffer", 1000.0, 10.0),
+            timed("crop", 1000.0, 10.0),
+            timed("jumprope", 1000.0, 10.0),
+            timed("ropey", 1000.0, 100.0),
+            timed("string", 1000.0, 10.0),
+        ];
+        let after = [
+            // Slower by more than the threshold, by less, faster, and within the noise.
+            timed("buffer", 1500.0, 10.0),
+            timed("crop", 1030.0, 10.0),
+            timed("jumprope", 500.0, 10.0),
+            timed("ropey", 1100.0, 100.0),
+            timed("vec", 1000.0, 10.0),
+        ];
+        let (report, regressions) = comparison(&before, &after, 5.0);
+        assert_eq!(regressions, 1);
+        assert_eq!(
+            columns(&report),
+            [
+                vec![
+                    "insert/buffer/1024",
+                    "1.00 µs",
+                    "1.50 µs",
+                    "1.50x",
+                    "REGRESSED"
+                ],
+                vec!["insert/crop/1024", "1.00 µs", "1.03 µs", "1.03x", "slower"],
+                vec![
+                    "insert/jumprope/1024",
+                    "1.00 µs",
+                    "500.0 ns",
+                    "0.50x",
+                    "faster"
+                ],
+                vec!["insert/ropey/1024", "1.00 µs", "1.10 µs", "1.10x"],
+                vec!["insert/vec/1024", "1.00 µs", "new"],
+                vec!["insert/string/1024", "1.00 µs", "removed"],
+            ]
+        );
+
+        // A higher threshold lets the big slowdown through, so `compare` would exit with 0.
+        let (report, regressions) = comparison(&before, &after, 60.0);
+        assert_eq!(regressions, 0);
+        assert_eq!(columns(&report)[0][4], "slower");
+    }
+}
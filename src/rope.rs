@@ -1,8 +1,20 @@
+use crdt_testdata::TestPatch;
 use std::borrow::Cow;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
 
 pub trait Rope: From<String> {
     const NAME: &'static str;
     const EDITS_USE_BYTE_OFFSETS: bool = false;
+    /// Whether `line_of` and `line_to_offset` use the rope's own line index. Without one, every
+    /// line lookup counts line breaks from the start of the text, so it's O(n). Benchmarks which
+    /// look lines up name such ropes `<name>_fallback`, so the defaults aren't mistaken for the
+    /// rope's own speed.
+    const INDEXES_LINES: bool = false;
+    /// Whether `char_to_byte` and `byte_to_char` use the rope's own index, rather than the O(n)
+    /// defaults. Like `INDEXES_LINES`, ropes without one are labelled `_fallback`.
+    const INDEXES_CHAR_BYTE: bool = false;
 
     fn new() -> Self;
 
@@ -16,6 +28,11 @@ pub trait Rope: From<String> {
             self.insert_at(pos, ins_content);
         }
     }
+    /// Apply a batch of edits at once, e.g. one per cursor. Each position is relative to the
+    /// document after the edits before it, like the patches in a `TestTxn`.
+    fn apply_batch(&mut self, edits: &[TestPatch]) {
+        apply_each(self, edits);
+    }
     fn to_string(&self) -> String;
     fn get_string(&self) -> Cow<'_, str> {
         Cow::Owned(self.to_string())
@@ -30,4 +47,549 @@ pub trait Rope: From<String> {
         let string = self.to_string();
         re.find(string.as_str()).map(|m| m.start()).unwrap_or_else(|| self.byte_len())
     }
+
+    /// Build a rope from a stream of chunks. By default they're collected into a `String` first;
+    /// ropes with a builder override this to add them one at a time.
+    fn from_chunks<'a>(chunks: impl Iterator<Item = &'a str>) -> Self {
+        Self::from(chunks.collect::<String>())
+    }
+
+    /// Load a rope from a reader such as a file. By default this reads everything into a
+    /// `String` first; ropes with a builder override it to stream the text in chunk by chunk.
+    fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut string = String::new();
+        reader.read_to_string(&mut string)?;
+        Ok(Self::from(string))
+    }
+
+    /// Write the text out chunk by chunk.
+    fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut result = Ok(());
+        self.for_each_chunk(0..self.char_len(), |chunk| {
+            result = writer.write_all(chunk.as_bytes());
+            result.is_ok()
+        });
+        result
+    }
+
+    /// The length of `s` in the units used for offsets (bytes if `EDITS_USE_BYTE_OFFSETS`,
+    /// otherwise chars).
+    fn unit_len(s: &str) -> usize {
+        if Self::EDITS_USE_BYTE_OFFSETS {
+            s.len()
+        } else {
+            s.chars().count()
+        }
+    }
+
+    /// Call `f` on each chunk of the text between the offsets in `range` (in the same units as
+    /// edits), stopping early if it returns false.
+    fn for_each_chunk(&self, range: Range<usize>, f: impl FnMut(&str) -> bool);
+
+    /// The line containing the offset `pos`.
+    fn line_of(&self, pos: usize) -> usize {
+        let mut lines = 0;
+        self.for_each_chunk(0..pos, |chunk| {
+            lines += chunk.bytes().filter(|&b| b == b'\n').count();
+            true
+        });
+        lines
+    }
+
+    /// The offset of the start of `line`, or the end of the text if there aren't that many lines.
+    fn line_to_offset(&self, line: usize) -> usize {
+        if line == 0 {
+            return 0;
+        }
+        let mut lines = 0;
+        let mut offset = 0;
+        self.for_each_chunk(0..self.char_len(), |chunk| {
+            for (i, b) in chunk.bytes().enumerate() {
+                if b == b'\n' {
+                    lines += 1;
+                    if lines == line {
+                        offset += Self::unit_len(&chunk[..=i]);
+                        return false;
+                    }
+                }
+            }
+            offset += Self::unit_len(chunk);
+            true
+        });
+        offset
+    }
+
+    fn char_to_byte(&self, pos: usize) -> usize {
+        let mut bytes = 0;
+        if !Self::EDITS_USE_BYTE_OFFSETS {
+            self.for_each_chunk(0..pos, |chunk| {
+                bytes += chunk.len();
+                true
+            });
+            return bytes;
+        }
+        let mut chars = 0;
+        self.for_each_chunk(0..self.char_len(), |chunk| {
+            let len = chunk.chars().count();
+            if chars + len < pos {
+                chars += len;
+                bytes += chunk.len();
+                return true;
+            }
+            bytes += chunk
+                .char_indices()
+                .nth(pos - chars)
+                .map_or(chunk.len(), |(i, _)| i);
+            false
+        });
+        bytes
+    }
+
+    fn byte_to_char(&self, pos: usize) -> usize {
+        let mut chars = 0;
+        if Self::EDITS_USE_BYTE_OFFSETS {
+            self.for_each_chunk(0..pos, |chunk| {
+                chars += chunk.chars().count();
+                true
+            });
+            return chars;
+        }
+        let mut bytes = 0;
+        self.for_each_chunk(0..self.char_len(), |chunk| {
+            if bytes + chunk.len() < pos {
+                bytes += chunk.len();
+                chars += chunk.chars().count();
+                return true;
+            }
+            chars += chunk[..pos - bytes].chars().count();
+            false
+        });
+        chars
+    }
+
+    /// Call `f` with the length in bytes of each line, not counting its line break (`\n` or
+    /// `\r\n`). The text after the last line break is always a line, even if it's empty.
+    fn for_each_line(&self, mut f: impl FnMut(usize)) {
+        let mut len = 0;
+        // Whether the text so far ends in a '\r', which could be split from its '\n'.
+        let mut cr = false;
+        self.for_each_chunk(0..self.char_len(), |chunk| {
+            let mut rest = chunk;
+            while let Some(i) = rest.find('\n') {
+                let crlf = if i == 0 {
+                    cr
+                } else {
+                    rest.as_bytes()[i - 1] == b'\r'
+                };
+                f(len + i - crlf as usize);
+                len = 0;
+                cr = false;
+                rest = &rest[i + 1..];
+            }
+            len += rest.len();
+            if !rest.is_empty() {
+                cr = rest.ends_with('\r');
+            }
+            true
+        });
+        f(len);
+    }
+
+    /// Round `pos` down to the start of the char it's in. Only offsets in bytes can land inside
+    /// a char.
+    fn floor_char_boundary(&self, pos: usize) -> usize {
+        pos
+    }
+
+    /// The offset of the grapheme cluster boundary after `pos`, or the end of the text.
+    fn next_grapheme_boundary(&self, pos: usize) -> usize {
+        grapheme_boundary(self, pos, true)
+    }
+
+    /// The offset of the grapheme cluster boundary before `pos`, or 0.
+    fn prev_grapheme_boundary(&self, pos: usize) -> usize {
+        grapheme_boundary(self, pos, false)
+    }
+
+    /// The line containing `pos`, and the column of `pos` on it counted in `unit`.
+    fn offset_to_line_col(&self, pos: usize, unit: Unit) -> (usize, usize) {
+        offset_to_line_col_by_chunks(self, pos, unit)
+    }
+
+    /// The offset of column `col` (counted in `unit`) on `line`. The column must be within the
+    /// line; one in the middle of a char is rounded down to the start of it.
+    fn line_col_to_offset(&self, line: usize, col: usize, unit: Unit) -> usize {
+        line_col_to_offset_by_chunks(self, line, col, unit)
+    }
+}
+
+/// What a column is counted in. LSP uses UTF-16 code units unless the client says otherwise.
+#[derive(Clone, Copy)]
+pub enum Unit {
+    Char,
+    Byte,
+    Utf16,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 3] = [Unit::Char, Unit::Byte, Unit::Utf16];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Char => "char",
+            Unit::Byte => "byte",
+            Unit::Utf16 => "utf16",
+        }
+    }
+
+    /// The length of `s` in this unit.
+    pub fn count(self, s: &str) -> usize {
+        match self {
+            Unit::Char => s.chars().count(),
+            Unit::Byte => s.len(),
+            Unit::Utf16 => s.encode_utf16().count(),
+        }
+    }
+
+    fn count_char(self, c: char) -> usize {
+        match self {
+            Unit::Char => 1,
+            Unit::Byte => c.len_utf8(),
+            Unit::Utf16 => c.len_utf16(),
+        }
+    }
+}
+
+/// `offset_to_line_col` by counting the units in the chunks between the start of the line and
+/// `pos`.
+pub fn offset_to_line_col_by_chunks<R: Rope>(r: &R, pos: usize, unit: Unit) -> (usize, usize) {
+    let line = r.line_of(pos);
+    let mut col = 0;
+    r.for_each_chunk(r.line_to_offset(line)..pos, |chunk| {
+        col += unit.count(chunk);
+        true
+    });
+    (line, col)
+}
+
+/// `line_col_to_offset` by walking the chunks from the start of the line until `col` units have
+/// gone by.
+pub fn line_col_to_offset_by_chunks<R: Rope>(r: &R, line: usize, col: usize, unit: Unit) -> usize {
+    let mut offset = r.line_to_offset(line);
+    let mut remaining = col;
+    r.for_each_chunk(offset..r.char_len(), |chunk| {
+        let len = unit.count(chunk);
+        if len < remaining {
+            remaining -= len;
+            offset += R::unit_len(chunk);
+            return true;
+        }
+        for c in chunk.chars() {
+            let len = unit.count_char(c);
+            if len > remaining {
+                break;
+            }
+            remaining -= len;
+            offset += if R::EDITS_USE_BYTE_OFFSETS {
+                c.len_utf8()
+            } else {
+                1
+            };
+        }
+        false
+    });
+    offset
+}
+
+/// The length of a line without its line break, given its length in bytes and a way to get at
+/// its bytes.
+pub fn strip_line_break(mut len: usize, byte: impl Fn(usize) -> u8) -> usize {
+    if len > 0 && byte(len - 1) == b'\n' {
+        len -= 1;
+        if len > 0 && byte(len - 1) == b'\r' {
+            len -= 1;
+        }
+    }
+    len
+}
+
+/// Find the grapheme boundary after (or before) `pos` by copying the text around it into a
+/// string for a `GraphemeCursor`. Whenever the cursor needs to see more text, e.g. because the
+/// window ends in the middle of an emoji sequence, the window is doubled in that direction.
+pub fn grapheme_boundary<R: Rope>(r: &R, pos: usize, forward: bool) -> usize {
+    let len = r.char_len();
+    let (mut before, mut after) = (16, 64);
+    loop {
+        let start = r.floor_char_boundary(pos.saturating_sub(before));
+        let end = r.floor_char_boundary((pos + after).min(len));
+        let mut window = String::new();
+        r.for_each_chunk(start..pos, |chunk| {
+            window.push_str(chunk);
+            true
+        });
+        let split = window.len();
+        r.for_each_chunk(pos..end, |chunk| {
+            window.push_str(chunk);
+            true
+        });
+
+        // Pretend there's a byte either side of a window which doesn't reach the edge of the
+        // text, so the cursor asks for more instead of finding a boundary there.
+        let offset = (start > 0) as usize;
+        let total = offset + window.len() + (end < len) as usize;
+        let mut cursor = GraphemeCursor::new(offset + split, total, true);
+        let boundary = if forward {
+            cursor.next_boundary(&window, offset)
+        } else {
+            cursor.prev_boundary(&window, offset)
+        };
+        match boundary {
+            Ok(Some(b)) if b - offset >= split => {
+                return pos + R::unit_len(&window[split..b - offset]);
+            }
+            Ok(Some(b)) => return pos - R::unit_len(&window[b - offset..split]),
+            Ok(None) => return if forward { len } else { 0 },
+            Err(GraphemeIncomplete::PreContext(_) | GraphemeIncomplete::PrevChunk) => before *= 2,
+            Err(GraphemeIncomplete::NextChunk) => after *= 2,
+            Err(GraphemeIncomplete::InvalidOffset) => unreachable!(),
+        }
+    }
+}
+
+/// Apply a batch of edits one at a time.
+pub fn apply_each<R: Rope>(r: &mut R, edits: &[TestPatch]) {
+    for TestPatch(pos, del, ins) in edits {
+        r.edit_at(*pos, *del, ins);
+    }
+}
+
+/// Convert a batch of edits into (position, delete length, insert) spans in ascending order,
+/// with positions relative to the document before the batch. This only works if the edits don't
+/// overlap and all run in the same direction, as they do with multiple cursors.
+pub fn batch_spans<R: Rope>(edits: &[TestPatch]) -> Option<Vec<(usize, usize, &str)>> {
+    let ascending = edits
+        .windows(2)
+        .all(|w| w[1].0 >= w[0].0 + R::unit_len(&w[0].2));
+    if ascending {
+        let mut shift = 0isize;
+        let spans = edits
+            .iter()
+            .map(|TestPatch(pos, del, ins)| {
+                let span = ((*pos as isize - shift) as usize, *del, ins.as_str());
+                shift += R::unit_len(ins) as isize - *del as isize;
+                span
+            })
+            .collect();
+        return Some(spans);
+    }
+
+    // Edits before the previous one don't see its effects, so their positions are unchanged.
+    let descending = edits.windows(2).all(|w| w[1].0 + w[1].1 <= w[0].0);
+    if descending {
+        let spans = edits
+            .iter()
+            .rev()
+            .map(|TestPatch(pos, del, ins)| (*pos, *del, ins.as_str()))
+            .collect();
+        return Some(spans);
+    }
+    None
+}
+
+/// Whether a batch is big enough that rebuilding the document is cheaper than applying each
+/// edit: roughly one edit for every 256 bytes.
+pub fn worth_rebuilding<R: Rope>(r: &R, spans: &[(usize, usize, &str)]) -> bool {
+    spans.len() * 256 >= r.byte_len()
+}
+
+/// Call `f` with the pieces of the document produced by applying `spans` (from `batch_spans`)
+/// to `r`, in order.
+pub fn for_each_piece<R: Rope>(r: &R, spans: &[(usize, usize, &str)], mut f: impl FnMut(&str)) {
+    let mut last = 0;
+    for (pos, del, ins) in spans {
+        r.for_each_chunk(last..*pos, |chunk| {
+            f(chunk);
+            true
+        });
+        f(ins);
+        last = pos + del;
+    }
+    r.for_each_chunk(last..r.char_len(), |chunk| {
+        f(chunk);
+        true
+    });
+}
+
+/// Apply a batch by collecting the edited document into a `String` and converting that back
+/// into a rope, falling back to `apply_each` for small or unsorted batches.
+pub fn rebuild_from_string<R: Rope>(r: &mut R, edits: &[TestPatch]) {
+    match batch_spans::<R>(edits) {
+        Some(spans) if worth_rebuilding(r, &spans) => {
+            let mut string = String::with_capacity(r.byte_len());
+            for_each_piece(r, &spans, |piece| string.push_str(piece));
+            *r = R::from(string);
+        }
+        _ => apply_each(r, edits),
+    }
+}
+
+/// Read UTF-8 text in chunks of up to 64KB, calling `f` with each one. Chunks always end on a
+/// char boundary.
+pub fn read_chunks(mut reader: impl Read, mut f: impl FnMut(&str)) -> io::Result<()> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "stream is not valid UTF-8");
+    let mut buf = vec![0; 64 * 1024];
+    let mut filled = 0;
+    loop {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+        let valid = match std::str::from_utf8(&buf[..filled]) {
+            Ok(chunk) => {
+                f(chunk);
+                filled
+            }
+            // A char split across reads.
+            Err(e) if e.error_len().is_none() => {
+                let valid = e.valid_up_to();
+                f(std::str::from_utf8(&buf[..valid]).unwrap());
+                valid
+            }
+            Err(_) => return Err(invalid()),
+        };
+        buf.copy_within(valid..filled, 0);
+        filled -= valid;
+    }
+    if filled > 0 {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crdt_testdata::{TestData, TestTxn};
+    use ropey::Rope as Ropey;
+    use text_buffer::Buffer;
+
+    fn patches(edits: &[(usize, usize, &str)]) -> Vec<TestPatch> {
+        edits
+            .iter()
+            .map(|&(pos, del, ins)| TestPatch(pos, del, ins.to_string()))
+            .collect()
+    }
+
+    /// Apply `edits` to `text` both as a batch and one at a time, and check both give `expected`.
+    fn check_batch<R: Rope + for<'a> From<&'a str>>(
+        text: &str,
+        edits: &[TestPatch],
+        expected: &str,
+    ) {
+        let mut each = R::from(text);
+        apply_each(&mut each, edits);
+        assert_eq!(each.get_string(), expected, "{} one at a time", R::NAME);
+        let mut batch = R::from(text);
+        batch.apply_batch(edits);
+        assert_eq!(batch.get_string(), expected, "{} as a batch", R::NAME);
+    }
+
+    /// `check_batch` for every rope, with `edits` in chars.
+    fn check_all(text: &str, edits: &[TestPatch], expected: &str) {
+        let data = TestData {
+            using_byte_positions: false,
+            start_content: text.into(),
+            end_content: expected.into(),
+            txns: vec![TestTxn {
+                patches: edits.to_vec(),
+            }],
+        };
+        let byte_edits = &data.chars_to_bytes().txns[0].patches;
+        check_batch::<Buffer>(text, edits, expected);
+        check_batch::<crop::Rope>(text, byte_edits, expected);
+        check_batch::<jumprope::JumpRope>(text, edits, expected);
+        check_batch::<Ropey>(text, edits, expected);
+    }
+
+    #[test]
+    fn ascending_batch() {
+        // Each position accounts for the edits before it.
+        let edits = patches(&[(1, 0, "x"), (4, 1, "é"), (7, 0, "yz")]);
+        let spans = batch_spans::<Ropey>(&edits).unwrap();
+        assert_eq!(spans, [(1, 0, "x"), (3, 1, "é"), (6, 0, "yz")]);
+        check_all("abcdefgh", &edits, "axbcéefyzgh");
+    }
+
+    #[test]
+    fn descending_batch() {
+        let edits = patches(&[(6, 0, "yz"), (3, 1, "é"), (1, 0, "x")]);
+        let spans = batch_spans::<Ropey>(&edits).unwrap();
+        assert_eq!(spans, [(1, 0, "x"), (3, 1, "é"), (6, 0, "yz")]);
+        check_all("abcdefgh", &edits, "axbcéefyzgh");
+    }
+
+    #[test]
+    fn deleting_batch() {
+        let edits = patches(&[(0, 2, ""), (3, 2, ""), (4, 1, "")]);
+        let spans = batch_spans::<Ropey>(&edits).unwrap();
+        assert_eq!(spans, [(0, 2, ""), (5, 2, ""), (8, 1, "")]);
+        check_all("abcdefghij", &edits, "cdehj");
+    }
+
+    #[test]
+    fn overlapping_batch() {
+        // The second edit lands inside the text the first inserted, so the batch has no order.
+        let edits = patches(&[(2, 0, "xyz"), (3, 1, "w"), (0, 1, "")]);
+        assert!(batch_spans::<Ropey>(&edits).is_none());
+        check_all("abcdef", &edits, "bxwzcdef");
+    }
+
+    #[test]
+    fn byte_offset_batch() {
+        // With byte offsets, an insertion shifts the edits after it by its length in bytes.
+        let edits = patches(&[(0, 0, "é"), (3, 1, "")]);
+        let spans = batch_spans::<crop::Rope>(&edits).unwrap();
+        assert_eq!(spans, [(0, 0, "é"), (1, 1, "")]);
+    }
+
+    /// Check a column in the middle of a char is rounded down to the start of it.
+    fn check_mid_char<R: Rope + for<'a> From<&'a str>>() {
+        let text = "x\naé😀b";
+        let r = R::from(text);
+        let offset = |chars: usize| {
+            if R::EDITS_USE_BYTE_OFFSETS {
+                text.char_indices().nth(chars).unwrap().0
+            } else {
+                chars
+            }
+        };
+        // Byte column 2 is inside the é.
+        assert_eq!(
+            r.line_col_to_offset(1, 2, Unit::Byte),
+            offset(3),
+            "{}",
+            R::NAME
+        );
+        // UTF-16 column 3 is between the 😀's surrogates.
+        assert_eq!(
+            r.line_col_to_offset(1, 3, Unit::Utf16),
+            offset(4),
+            "{}",
+            R::NAME
+        );
+        for unit in Unit::ALL {
+            let end = r.line_col_to_offset(1, unit.count("aé😀"), unit);
+            assert_eq!(end, offset(5), "{} {}", R::NAME, unit.name());
+        }
+    }
+
+    #[test]
+    fn mid_char_column() {
+        check_mid_char::<Buffer>();
+        check_mid_char::<crop::Rope>();
+        check_mid_char::<jumprope::JumpRope>();
+        check_mid_char::<Ropey>();
+    }
 }